@@ -1,5 +1,8 @@
+use ring::digest;
 use serde_derive::{Deserialize, Serialize};
 
+use crate::base64_encode;
+
 #[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Algorithm {
     RS256,
@@ -12,3 +15,27 @@ pub enum Algorithm {
     ES384,
     ES512,
 }
+
+impl Algorithm {
+    /// Base64url encoding of the left-most half of the hash of `value`, as used by the
+    /// `at_hash` and `c_hash` claims.
+    pub fn half_hash(&self, value: &[u8]) -> String {
+        let digest_algorithm = match self {
+            Algorithm::RS256 | Algorithm::HS256 | Algorithm::ES256 => &digest::SHA256,
+            Algorithm::RS384 | Algorithm::HS384 | Algorithm::ES384 => &digest::SHA384,
+            Algorithm::RS512 | Algorithm::HS512 | Algorithm::ES512 => &digest::SHA512,
+        };
+        let digest = digest::digest(digest_algorithm, value);
+        let hash = digest.as_ref();
+        base64_encode(&hash[..hash.len() / 2])
+    }
+}
+
+#[test]
+fn test_half_hash() {
+    // https://openid.net/specs/openid-connect-core-1_0.html#code-id_tokenExample
+    assert_eq!(
+        Algorithm::RS256.half_hash(b"jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y"),
+        "77QmUPtjPfzWtF2AnpK9RQ"
+    );
+}
@@ -0,0 +1,140 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+use serde_derive::Deserialize;
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::client_policy::ClientPolicy;
+use crate::error::{Error, InvalidError::InvalidClaims};
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+use crate::key_provider::JwksKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::token::Token;
+
+const APP_CHECK_KEYS_URL: &str = "https://firebaseappcheck.googleapis.com/v1/jwks";
+const APP_CHECK_ISSUER: &str = "https://firebaseappcheck.googleapis.com";
+
+#[cfg(feature = "blocking")]
+pub type AppCheckClient = GenericAppCheckClient<RefCell<JwksKeyProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioAppCheckClient = GenericAppCheckClient<Arc<Mutex<JwksKeyProvider>>>;
+
+// https://firebase.google.com/docs/app-check/custom-resource-backend#other
+#[derive(Deserialize, Clone, Debug)]
+pub struct AppCheckPayload {
+    /// The Firebase app ID of the attested client
+    #[serde(rename = "sub")]
+    pub app_id: String,
+}
+
+/// Verifies Firebase App Check tokens for a single project.
+pub struct GenericAppCheckClient<KP> {
+    client: GenericClient<KP>,
+}
+
+impl<KP> From<GenericClient<KP>> for GenericAppCheckClient<KP> {
+    fn from(client: GenericClient<KP>) -> Self {
+        Self { client }
+    }
+}
+
+fn app_check_builder<KP>(
+    project_number: &str,
+    project_id: Option<&str>,
+    key_provider: KP,
+) -> GenericClientBuilder<KP> {
+    // `aud` lists both `projects/<number>` and `projects/<id>`
+    let issuer = format!("{APP_CHECK_ISSUER}/{project_number}");
+    let builder = GenericClientBuilder::with_key_provider(
+        &format!("projects/{project_number}"),
+        key_provider,
+    )
+    .issuers(&[&issuer])
+    .allow_multiple_audiences();
+    match project_id {
+        Some(project_id) => builder.client(
+            &format!("projects/{project_id}"),
+            ClientPolicy::new().issuers(&[&issuer]),
+        ),
+        None => builder,
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl AppCheckClient {
+    /// A [GenericClientBuilder] with the audience, issuer and keys of App Check tokens.
+    pub fn builder(project_number: &str) -> GenericClientBuilder<RefCell<JwksKeyProvider>> {
+        let key_provider = JwksKeyProvider::new(APP_CHECK_KEYS_URL);
+        app_check_builder(project_number, None, RefCell::new(key_provider))
+    }
+    /// Like [AppCheckClient::builder], but also accept tokens whose `aud` only lists
+    /// `projects/<project_id>`.
+    pub fn project_id_builder(
+        project_number: &str,
+        project_id: &str,
+    ) -> GenericClientBuilder<RefCell<JwksKeyProvider>> {
+        let key_provider = JwksKeyProvider::new(APP_CHECK_KEYS_URL);
+        app_check_builder(project_number, Some(project_id), RefCell::new(key_provider))
+    }
+    pub fn new(project_number: &str) -> Self {
+        Self::from(Self::builder(project_number).build())
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioAppCheckClient {
+    /// A [GenericClientBuilder] with the audience, issuer and keys of App Check tokens.
+    pub fn builder(project_number: &str) -> GenericClientBuilder<Arc<Mutex<JwksKeyProvider>>> {
+        let key_provider = JwksKeyProvider::new(APP_CHECK_KEYS_URL);
+        app_check_builder(project_number, None, Arc::new(Mutex::new(key_provider)))
+    }
+    /// Like [TokioAppCheckClient::builder], but also accept tokens whose `aud` only lists
+    /// `projects/<project_id>`.
+    pub fn project_id_builder(
+        project_number: &str,
+        project_id: &str,
+    ) -> GenericClientBuilder<Arc<Mutex<JwksKeyProvider>>> {
+        let key_provider = JwksKeyProvider::new(APP_CHECK_KEYS_URL);
+        let key_provider = Arc::new(Mutex::new(key_provider));
+        app_check_builder(project_number, Some(project_id), key_provider)
+    }
+    pub fn new(project_number: &str) -> Self {
+        Self::from(Self::builder(project_number).build())
+    }
+}
+
+fn check_app_check_token(token: Token<AppCheckPayload>) -> Result<Token<AppCheckPayload>, Error> {
+    if token.payload.app_id.is_empty() {
+        return Err(Error::InvalidToken(InvalidClaims("sub".to_string())));
+    }
+    Ok(token)
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> GenericAppCheckClient<RefCell<KP>> {
+    /// Verify the value of the `X-Firebase-AppCheck` request header.
+    pub fn verify_token(&self, token_string: &str) -> Result<Token<AppCheckPayload>, Error> {
+        self.client
+            .verify_token_with_check(token_string, check_app_check_token)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> GenericAppCheckClient<Arc<Mutex<KP>>> {
+    /// Verify the value of the `X-Firebase-AppCheck` request header.
+    pub async fn verify_token_async(
+        &self,
+        token_string: &str,
+    ) -> Result<Token<AppCheckPayload>, Error> {
+        self.client
+            .verify_token_with_check_async(token_string, check_app_check_token)
+            .await
+    }
+}
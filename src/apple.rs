@@ -0,0 +1,145 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer};
+use serde_derive::Deserialize;
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::error::Error;
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+use crate::key_provider::JwksKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::token::Token;
+
+const APPLE_KEYS_URL: &str = "https://appleid.apple.com/auth/keys";
+const APPLE_ISSUER: &str = "https://appleid.apple.com";
+
+#[cfg(feature = "blocking")]
+pub type AppleClient = GenericAppleClient<RefCell<JwksKeyProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioAppleClient = GenericAppleClient<Arc<Mutex<JwksKeyProvider>>>;
+
+// https://developer.apple.com/documentation/signinwithapple/authenticating-users-with-sign-in-with-apple#Retrieve-the-users-information-from-Apple-ID-servers
+#[derive(Deserialize, Clone, Debug)]
+pub struct ApplePayload {
+    /// Only present if the email scope was requested
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "apple_bool")]
+    pub email_verified: Option<bool>,
+    /// True for addresses of Apple's private email relay
+    #[serde(default, deserialize_with = "apple_bool")]
+    pub is_private_email: Option<bool>,
+    pub real_user_status: Option<RealUserStatus>,
+    pub nonce: Option<String>,
+    pub nonce_supported: Option<bool>,
+    pub auth_time: Option<u64>,
+    pub at_hash: Option<String>,
+    pub c_hash: Option<String>,
+}
+
+/// Whether the user appears to be a real person
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RealUserStatus {
+    Unsupported,
+    Unknown,
+    LikelyReal,
+    /// A status Apple added after this version
+    Unrecognized(u8),
+}
+
+impl<'de> Deserialize<'de> for RealUserStatus {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match u8::deserialize(deserializer)? {
+            0 => RealUserStatus::Unsupported,
+            1 => RealUserStatus::Unknown,
+            2 => RealUserStatus::LikelyReal,
+            status => RealUserStatus::Unrecognized(status),
+        })
+    }
+}
+
+/// Apple sends some boolean claims as the strings `"true"` and `"false"`.
+fn apple_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+    match Option::<BoolOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(BoolOrString::Bool(value)) => Ok(Some(value)),
+        Some(BoolOrString::String(value)) => {
+            value.parse().map(Some).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+/// Verifies Sign in with Apple identity tokens.
+pub struct GenericAppleClient<KP> {
+    client: GenericClient<KP>,
+}
+
+impl<KP> From<GenericClient<KP>> for GenericAppleClient<KP> {
+    fn from(client: GenericClient<KP>) -> Self {
+        Self { client }
+    }
+}
+
+impl<KP> GenericAppleClient<KP> {
+    pub(crate) fn issuers(&self) -> Vec<String> {
+        self.client.issuers()
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl AppleClient {
+    /// A [GenericClientBuilder] with the issuer and keys of Apple identity tokens, for the
+    /// bundle ID or services ID `client_id`.
+    pub fn builder(client_id: &str) -> GenericClientBuilder<RefCell<JwksKeyProvider>> {
+        let key_provider = RefCell::new(JwksKeyProvider::new(APPLE_KEYS_URL));
+        GenericClientBuilder::with_key_provider(client_id, key_provider).issuers(&[APPLE_ISSUER])
+    }
+    pub fn new(client_id: &str) -> Self {
+        Self::from(Self::builder(client_id).build())
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioAppleClient {
+    /// A [GenericClientBuilder] with the issuer and keys of Apple identity tokens, for the
+    /// bundle ID or services ID `client_id`.
+    pub fn builder(client_id: &str) -> GenericClientBuilder<Arc<Mutex<JwksKeyProvider>>> {
+        let key_provider = Arc::new(Mutex::new(JwksKeyProvider::new(APPLE_KEYS_URL)));
+        GenericClientBuilder::with_key_provider(client_id, key_provider).issuers(&[APPLE_ISSUER])
+    }
+    pub fn new(client_id: &str) -> Self {
+        Self::from(Self::builder(client_id).build())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> GenericAppleClient<RefCell<KP>> {
+    pub fn verify_id_token(&self, token_string: &str) -> Result<Token<ApplePayload>, Error> {
+        self.client.verify_token_with_payload(token_string)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> GenericAppleClient<Arc<Mutex<KP>>> {
+    pub async fn verify_id_token_async(
+        &self,
+        token_string: &str,
+    ) -> Result<Token<ApplePayload>, Error> {
+        self.client
+            .verify_token_with_payload_async(token_string)
+            .await
+    }
+}
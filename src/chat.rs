@@ -0,0 +1,123 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::bearer_token;
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::error::Error;
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+use crate::key_provider::JwksKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::token::{IdPayload, Token};
+
+const CHAT_ISSUER: &str = "chat@system.gserviceaccount.com";
+// JWK form of https://www.googleapis.com/service_accounts/v1/metadata/x509/chat@system.gserviceaccount.com
+const CHAT_KEYS_URL: &str =
+    "https://www.googleapis.com/service_accounts/v1/jwk/chat@system.gserviceaccount.com";
+
+#[cfg(feature = "blocking")]
+pub type ChatClient = GenericChatClient<RefCell<JwksKeyProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioChatClient = GenericChatClient<Arc<Mutex<JwksKeyProvider>>>;
+
+/// Verifies the bearer tokens of requests sent by Google Chat to an HTTP app.
+///
+/// When the app's authentication audience is its project number, Chat signs tokens with
+/// its own service account keys. When the audience is the app URL, Chat sends a Google
+/// ID token for the `chat@system.gserviceaccount.com` account instead.
+// https://developers.google.com/workspace/chat/authenticate-authorize-chat-app#verify-requests
+pub struct GenericChatClient<KP> {
+    client: GenericClient<KP>,
+}
+
+impl<KP> From<GenericClient<KP>> for GenericChatClient<KP> {
+    fn from(client: GenericClient<KP>) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl ChatClient {
+    /// A [GenericClientBuilder] for tokens with the app's project number as audience.
+    pub fn project_number_builder(
+        project_number: &str,
+    ) -> GenericClientBuilder<RefCell<JwksKeyProvider>> {
+        let key_provider = RefCell::new(JwksKeyProvider::new(CHAT_KEYS_URL));
+        GenericClientBuilder::with_key_provider(project_number, key_provider)
+            .issuers(&[CHAT_ISSUER])
+            .allow_missing_subject()
+    }
+    /// A [GenericClientBuilder] for tokens with the app URL as audience.
+    pub fn app_url_builder(app_url: &str) -> GenericClientBuilder<RefCell<JwksKeyProvider>> {
+        GenericClientBuilder::<RefCell<JwksKeyProvider>>::new(app_url)
+    }
+    pub fn for_project_number(project_number: &str) -> Self {
+        Self::from(Self::project_number_builder(project_number).build())
+    }
+    pub fn for_app_url(app_url: &str) -> Self {
+        Self::from(Self::app_url_builder(app_url).build())
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioChatClient {
+    /// A [GenericClientBuilder] for tokens with the app's project number as audience.
+    pub fn project_number_builder(
+        project_number: &str,
+    ) -> GenericClientBuilder<Arc<Mutex<JwksKeyProvider>>> {
+        let key_provider = Arc::new(Mutex::new(JwksKeyProvider::new(CHAT_KEYS_URL)));
+        GenericClientBuilder::with_key_provider(project_number, key_provider)
+            .issuers(&[CHAT_ISSUER])
+            .allow_missing_subject()
+    }
+    /// A [GenericClientBuilder] for tokens with the app URL as audience.
+    pub fn app_url_builder(app_url: &str) -> GenericClientBuilder<Arc<Mutex<JwksKeyProvider>>> {
+        GenericClientBuilder::<Arc<Mutex<JwksKeyProvider>>>::new(app_url)
+    }
+    pub fn for_project_number(project_number: &str) -> Self {
+        Self::from(Self::project_number_builder(project_number).build())
+    }
+    pub fn for_app_url(app_url: &str) -> Self {
+        Self::from(Self::app_url_builder(app_url).build())
+    }
+}
+
+fn check_chat_token(token: Token<IdPayload>) -> Result<Token<IdPayload>, Error> {
+    if token.claims.issuer == CHAT_ISSUER {
+        return Ok(token);
+    }
+    // app URL audience: a Google ID token for the Chat service account
+    if token.payload.email.as_deref() != Some(CHAT_ISSUER) {
+        return Err(Error::EmailNotAllowed);
+    }
+    if token.payload.email_verified != Some(true) {
+        return Err(Error::EmailNotVerified);
+    }
+    Ok(token)
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> GenericChatClient<RefCell<KP>> {
+    /// Verify the `Authorization` header of a Chat request, with or without the `Bearer` scheme.
+    pub fn verify(&self, authorization: &str) -> Result<Token<IdPayload>, Error> {
+        self.client
+            .verify_token_with_check(bearer_token(authorization), check_chat_token)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> GenericChatClient<Arc<Mutex<KP>>> {
+    /// Verify the `Authorization` header of a Chat request, with or without the `Bearer` scheme.
+    pub async fn verify_async(&self, authorization: &str) -> Result<Token<IdPayload>, Error> {
+        self.client
+            .verify_token_with_check_async(bearer_token(authorization), check_chat_token)
+            .await
+    }
+}
@@ -1,20 +1,27 @@
 #[cfg(feature = "blocking")]
 use std::cell::RefCell;
-#[cfg(feature = "async")]
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 #[cfg(feature = "async")]
 use tokio::sync::Mutex;
 
-use crate::error::Error;
+use crate::algorithm::Algorithm;
+use crate::client_policy::ClientPolicy;
+use crate::email_policy::EmailPolicy;
+use crate::error::{Error, InvalidError::InvalidClaims, NonceError};
 #[cfg(feature = "async")]
 use crate::key_provider::AsyncKeyProvider;
 use crate::key_provider::GoogleKeyProvider;
 #[cfg(feature = "blocking")]
 use crate::key_provider::KeyProvider;
+use crate::nonce::NonceStore;
+use crate::policy_handle::PolicyHandle;
+use crate::replay::ReplayCache;
 use crate::token::IdPayload;
 use crate::token::Token;
-use crate::unverified_token::UnverifiedToken;
+use crate::unverified_token::{AudienceMatch, UnverifiedToken, Validation};
 use serde::Deserialize;
+use serde_derive::Deserialize;
 
 #[cfg(feature = "blocking")]
 pub type Client = GenericClient<RefCell<GoogleKeyProvider>>;
@@ -23,65 +30,339 @@ pub type Client = GenericClient<RefCell<GoogleKeyProvider>>;
 pub type TokioClient = GenericClient<Arc<Mutex<GoogleKeyProvider>>>;
 
 pub struct GenericClientBuilder<KP> {
-    client_id: String,
+    validation: Validation,
     key_provider: KP,
-    check_expiration: bool,
+    email_policy: Option<EmailPolicy>,
+    nonce_store: Option<Arc<dyn NonceStore>>,
+    replay_cache: Option<Arc<dyn ReplayCache>>,
+    /// The rules before a configuration was applied, see [Policy::base]
+    #[cfg(feature = "config")]
+    base: Option<Arc<Policy>>,
 }
 
 #[cfg(feature = "blocking")]
 impl<KP: Default> GenericClientBuilder<RefCell<KP>> {
     pub fn new(client_id: &str) -> Self {
-        Self {
-            client_id: client_id.to_owned(),
-            key_provider: RefCell::new(KP::default()),
-            check_expiration: true,
-        }
+        GenericClientBuilder::with_key_provider(client_id, RefCell::new(KP::default()))
     }
     pub fn custom_key_provider<T>(self, provider: T) -> GenericClientBuilder<RefCell<T>> {
-        GenericClientBuilder {
-            client_id: self.client_id,
-            key_provider: RefCell::new(provider),
-            check_expiration: self.check_expiration,
-        }
+        self.replace_key_provider(RefCell::new(provider))
     }
 }
 
 #[cfg(feature = "async")]
 impl<KP: Default> GenericClientBuilder<Arc<Mutex<KP>>> {
     pub fn new(client_id: &str) -> Self {
-        Self {
-            client_id: client_id.to_owned(),
-            key_provider: Arc::new(Mutex::new(KP::default())),
-            check_expiration: true,
-        }
+        GenericClientBuilder::with_key_provider(client_id, Arc::new(Mutex::new(KP::default())))
     }
     pub fn custom_key_provider<T>(self, provider: T) -> GenericClientBuilder<Arc<Mutex<T>>> {
-        GenericClientBuilder {
-            client_id: self.client_id,
-            key_provider: Arc::new(Mutex::new(provider)),
-            check_expiration: self.check_expiration,
-        }
+        self.replace_key_provider(Arc::new(Mutex::new(provider)))
     }
 }
 
 impl<KP> GenericClientBuilder<KP> {
+    pub(crate) fn with_key_provider(client_id: &str, key_provider: KP) -> Self {
+        Self {
+            validation: Validation::new(client_id),
+            key_provider,
+            email_policy: None,
+            nonce_store: None,
+            replay_cache: None,
+            #[cfg(feature = "config")]
+            base: None,
+        }
+    }
+    fn replace_key_provider<T>(self, key_provider: T) -> GenericClientBuilder<T> {
+        GenericClientBuilder {
+            validation: self.validation,
+            key_provider,
+            email_policy: self.email_policy,
+            nonce_store: self.nonce_store,
+            replay_cache: self.replay_cache,
+            #[cfg(feature = "config")]
+            base: self.base,
+        }
+    }
     pub fn unsafe_ignore_expiration(mut self) -> Self {
-        self.check_expiration = false;
+        self.validation.check_expiration = false;
+        self
+    }
+    /// Tolerate this much clock skew between the issuer and this host when checking
+    /// the `exp` and `nbf` claims.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.validation.leeway = leeway.as_secs();
+        self
+    }
+    /// Accept tokens without an `exp` claim, and check the expiration of the others.
+    pub(crate) fn allow_missing_expiration(mut self) -> Self {
+        self.validation.require_expiration = false;
+        self
+    }
+    /// Accept tokens whose `aud` array lists other audiences besides this client, without
+    /// an `azp` claim naming it.
+    pub(crate) fn allow_multiple_audiences(mut self) -> Self {
+        self.validation.allow_multiple_audiences = true;
+        self
+    }
+    /// Accept tokens without a `sub` claim, such as those signed by Google service accounts.
+    pub(crate) fn allow_missing_subject(mut self) -> Self {
+        self.validation.require_subject = false;
+        self
+    }
+    /// Accept tokens from these issuers instead of Google's sign-in issuers.
+    /// An issuer may contain a `{tenantid}` placeholder for the token's `tid` claim.
+    pub fn issuers(mut self, issuers: &[&str]) -> Self {
+        self.validation.issuers = issuers.iter().map(|iss| iss.to_string()).collect();
+        self
+    }
+    /// Only accept tokens whose `tid` claim is one of these tenant IDs.
+    pub fn tenants(mut self, tenants: &[&str]) -> Self {
+        self.validation.tenants = tenants.iter().map(|tid| tid.to_string()).collect();
+        self
+    }
+    /// Treat the audience as the URL of a service, and accept tokens whose `aud` is that URL
+    /// or a path below it, as sent by Cloud Tasks, Cloud Scheduler and Cloud Run invokers.
+    pub fn audience_url_prefix(mut self) -> Self {
+        self.validation.audience_match = AudienceMatch::UrlPrefix;
+        self
+    }
+    /// Only accept `verify_id_token` callers whose verified email matches one of the
+    /// service account patterns, e.g. `*@my-project.iam.gserviceaccount.com`.
+    ///
+    /// The patterns are added to the allow lists of the [EmailPolicy] of this builder, which
+    /// then rejects every address it does not allow. An empty list rejects every caller.
+    pub fn service_accounts(mut self, patterns: &[&str]) -> Self {
+        let policy = self.email_policy.take().unwrap_or_default().deny_unlisted();
+        self.email_policy(patterns.iter().fold(policy, |policy, pattern| {
+            policy.allow_email_pattern(pattern)
+        }))
+    }
+    /// Apply an [EmailPolicy] to the tokens returned by `verify_id_token`.
+    /// Replaces the policy of an earlier [GenericClientBuilder::service_accounts] call.
+    pub fn email_policy(mut self, policy: EmailPolicy) -> Self {
+        self.email_policy = Some(policy);
+        self
+    }
+    /// Require every token to carry a `nonce` issued by `store`, and consume it.
+    pub fn nonce_store(mut self, store: Arc<dyn NonceStore>) -> Self {
+        self.nonce_store = Some(store);
+        self
+    }
+    /// Reject tokens that were already accepted by this client, until they expire.
+    pub fn replay_cache(mut self, cache: Arc<dyn ReplayCache>) -> Self {
+        self.replay_cache = Some(cache);
+        self
+    }
+    /// Also accept tokens for another OAuth client ID, checked with its own [ClientPolicy]
+    /// instead of the [EmailPolicy] of this builder. All client IDs share the key provider,
+    /// and [Token::client_id] tells which one a token was issued for.
+    pub fn client(mut self, client_id: &str, policy: ClientPolicy) -> Self {
+        self.validation.clients.insert(client_id.to_owned(), policy);
         self
     }
+    /// Remember the rules before a configuration is applied, unless already known.
+    #[cfg(feature = "config")]
+    pub(crate) fn before_config(mut self) -> Self {
+        if self.base.is_none() {
+            self.base = Some(Arc::new(Policy {
+                validation: self.validation.clone(),
+                email_policy: self.email_policy.clone(),
+                base: None,
+            }));
+        }
+        self
+    }
+    /// The rules of this builder, without its key provider, nonce store and replay cache
+    pub(crate) fn into_policy(self) -> Policy {
+        Policy {
+            validation: self.validation,
+            email_policy: self.email_policy,
+            #[cfg(feature = "config")]
+            base: self.base,
+        }
+    }
     pub fn build(self) -> GenericClient<KP> {
+        let GenericClientBuilder {
+            validation,
+            key_provider,
+            email_policy,
+            nonce_store,
+            replay_cache,
+            #[cfg(feature = "config")]
+            base,
+        } = self;
+        let policy = Policy {
+            validation,
+            email_policy,
+            #[cfg(feature = "config")]
+            base,
+        };
         GenericClient {
-            client_id: self.client_id,
-            key_provider: self.key_provider,
-            check_expiration: self.check_expiration,
+            policy: Arc::new(RwLock::new(Arc::new(policy))),
+            key_provider,
+            nonce_store,
+            replay_cache,
         }
     }
 }
 
 pub struct GenericClient<T> {
-    client_id: String,
+    policy: Arc<RwLock<Arc<Policy>>>,
     key_provider: T,
-    check_expiration: bool,
+    nonce_store: Option<Arc<dyn NonceStore>>,
+    replay_cache: Option<Arc<dyn ReplayCache>>,
+}
+
+/// The rules of a [GenericClient] that a [PolicyHandle] can replace
+pub(crate) struct Policy {
+    validation: Validation,
+    email_policy: Option<EmailPolicy>,
+    /// The rules before a configuration was applied, whose issuers, email policy and
+    /// client policies a reloaded configuration starts from
+    #[cfg(feature = "config")]
+    base: Option<Arc<Policy>>,
+}
+
+#[cfg(feature = "config")]
+impl GenericClientBuilder<()> {
+    /// A builder for `client_id` with the rules of `policy`, except those that a configuration
+    /// may have set, which are back to what they were before any configuration
+    pub(crate) fn reconfigure(policy: &Arc<Policy>, client_id: &str) -> Self {
+        let base = policy.base.clone().unwrap_or_else(|| policy.clone());
+        let validation = Validation {
+            audience: client_id.to_owned(),
+            issuers: base.validation.issuers.clone(),
+            clients: base.validation.clients.clone(),
+            ..policy.validation.clone()
+        };
+        Self {
+            validation,
+            key_provider: (),
+            email_policy: base.email_policy.clone(),
+            nonce_store: None,
+            replay_cache: None,
+            base: Some(base),
+        }
+    }
+}
+
+impl Policy {
+    fn validate<P>(&self, token_string: &str) -> Result<UnverifiedToken<P>, Error>
+    where
+        for<'a> P: Deserialize<'a> + std::fmt::Debug,
+    {
+        UnverifiedToken::<P>::validate(token_string, &self.validation)
+    }
+
+    fn check_client<P>(&self, token: &Token<P>) -> Result<(), Error> {
+        match self.validation.clients.get(&token.client_id) {
+            Some(policy) => policy.check(&token.claims, &token.raw_payload),
+            None => Ok(()),
+        }
+    }
+
+    fn check_id_token(&self, token: Token<IdPayload>) -> Result<Token<IdPayload>, Error> {
+        let email_policy = match self.validation.clients.get(&token.client_id) {
+            Some(client) => client.email_policy.as_ref(),
+            None => self.email_policy.as_ref(),
+        };
+        if let Some(policy) = email_policy {
+            policy.check(&token.payload)?;
+        }
+        Ok(token)
+    }
+}
+
+/// An ID token claim that binds the token to another value returned alongside it
+#[derive(Clone, Copy)]
+enum HashClaim {
+    AccessToken,
+    Code,
+}
+
+#[derive(Deserialize)]
+struct NonceClaim {
+    nonce: Option<String>,
+}
+
+impl<T> GenericClient<T> {
+    /// The `iss` values this client currently accepts, for any of its client IDs
+    pub(crate) fn issuers(&self) -> Vec<String> {
+        let policy = self.policy();
+        let client_issuers = policy
+            .validation
+            .clients
+            .values()
+            .filter_map(|client| client.issuers.as_ref());
+        policy
+            .validation
+            .issuers
+            .iter()
+            .chain(client_issuers.flatten())
+            .cloned()
+            .collect()
+    }
+
+    /// A handle to replace the client IDs, issuers, email rules and leeway of this client
+    /// while it keeps its key provider and cache.
+    pub fn policy_handle(&self) -> PolicyHandle {
+        PolicyHandle::new(self.policy.clone())
+    }
+
+    /// The current policy, which stays the same for the whole verification of a token
+    fn policy(&self) -> Arc<Policy> {
+        self.policy.read().unwrap().clone()
+    }
+
+    /// Consume the nonce of a token and record it in the replay cache. Runs after every
+    /// check that may reject the token, so that a rejected token can be retried.
+    /// `token_id` identifies the token for replay protection, within its issuer.
+    fn consume_token<P>(
+        &self,
+        policy: &Policy,
+        token: Token<P>,
+        token_id: &str,
+    ) -> Result<Token<P>, Error> {
+        if let Some(ref store) = self.nonce_store {
+            let claim: NonceClaim = serde_json::from_str(&token.raw_payload)?;
+            store.consume(&claim.nonce.ok_or(NonceError::Missing)?)?;
+        }
+        // remembered for as long as the token is accepted, which is until evicted for
+        // tokens without an expiration or with the check disabled
+        let validation = &policy.validation;
+        let expires_at = match token.claims.expires_at {
+            Some(exp) if validation.check_expiration => exp.saturating_add(validation.leeway),
+            _ => u64::MAX,
+        };
+        // a jti is only unique for its issuer, and an issuer cannot contain a space
+        let replay_key = format!("{} {token_id}", token.claims.issuer);
+        if let Some(ref cache) = self.replay_cache
+            && !cache.insert(&replay_key, expires_at)
+        {
+            return Err(Error::Replayed);
+        }
+        Ok(token)
+    }
+
+    // https://openid.net/specs/openid-connect-core-1_0.html#HybridIDToken
+    fn check_hash(
+        token: &Token<IdPayload>,
+        algorithm: Algorithm,
+        claim: HashClaim,
+        value: &str,
+    ) -> Result<(), Error> {
+        let (name, expected) = match claim {
+            HashClaim::AccessToken => ("at_hash", &token.payload.at_hash),
+            HashClaim::Code => ("c_hash", &token.payload.c_hash),
+        };
+        let expected = expected
+            .as_deref()
+            .ok_or(Error::InvalidToken(InvalidClaims(name.to_string())))?;
+        if algorithm.half_hash(value.as_bytes()) != expected {
+            return Err(Error::HashMismatch(name.to_string()));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "blocking")]
@@ -110,9 +391,37 @@ impl<KP: KeyProvider> GenericClient<RefCell<KP>> {
     where
         for<'a> P: Deserialize<'a> + std::fmt::Debug,
     {
-        let unverified_token =
-            UnverifiedToken::<P>::validate(token_string, self.check_expiration, &self.client_id)?;
-        unverified_token.verify(&self.key_provider)
+        self.verify_with_policy(&self.policy(), token_string, |token, _| Ok(token))
+    }
+
+    /// Verify a token and apply the checks of a preset client, before its nonce and replay
+    /// cache entry are consumed.
+    pub(crate) fn verify_token_with_check<P>(
+        &self,
+        token_string: &str,
+        check: impl FnOnce(Token<P>) -> Result<Token<P>, Error>,
+    ) -> Result<Token<P>, Error>
+    where
+        for<'a> P: Deserialize<'a> + std::fmt::Debug,
+    {
+        self.verify_with_policy(&self.policy(), token_string, |token, _| check(token))
+    }
+
+    fn verify_with_policy<P>(
+        &self,
+        policy: &Policy,
+        token_string: &str,
+        check: impl FnOnce(Token<P>, Algorithm) -> Result<Token<P>, Error>,
+    ) -> Result<Token<P>, Error>
+    where
+        for<'a> P: Deserialize<'a> + std::fmt::Debug,
+    {
+        let unverified_token = policy.validate::<P>(token_string)?;
+        let algorithm = unverified_token.algorithm();
+        let token_id = unverified_token.token_id();
+        let token = unverified_token.verify(&self.key_provider)?;
+        policy.check_client(&token)?;
+        self.consume_token(policy, check(token, algorithm)?, &token_id)
     }
 
     pub fn verify_token(&self, token_string: &str) -> Result<Token<()>, Error> {
@@ -120,7 +429,42 @@ impl<KP: KeyProvider> GenericClient<RefCell<KP>> {
     }
 
     pub fn verify_id_token(&self, token_string: &str) -> Result<Token<IdPayload>, Error> {
-        self.verify_token_with_payload(token_string)
+        let policy = self.policy();
+        self.verify_with_policy(&policy, token_string, |token, _| {
+            policy.check_id_token(token)
+        })
+    }
+
+    /// Verify an ID token and check that its `at_hash` claim matches `access_token`.
+    pub fn verify_id_token_with_access_token(
+        &self,
+        token_string: &str,
+        access_token: &str,
+    ) -> Result<Token<IdPayload>, Error> {
+        self.verify_id_token_with_hash(token_string, HashClaim::AccessToken, access_token)
+    }
+
+    /// Verify an ID token and check that its `c_hash` claim matches the authorization `code`.
+    pub fn verify_id_token_with_code(
+        &self,
+        token_string: &str,
+        code: &str,
+    ) -> Result<Token<IdPayload>, Error> {
+        self.verify_id_token_with_hash(token_string, HashClaim::Code, code)
+    }
+
+    fn verify_id_token_with_hash(
+        &self,
+        token_string: &str,
+        claim: HashClaim,
+        value: &str,
+    ) -> Result<Token<IdPayload>, Error> {
+        let policy = self.policy();
+        self.verify_with_policy(&policy, token_string, |token, algorithm| {
+            let token = policy.check_id_token(token)?;
+            Self::check_hash(&token, algorithm, claim, value)?;
+            Ok(token)
+        })
     }
 }
 
@@ -133,9 +477,39 @@ impl<KP: AsyncKeyProvider> GenericClient<Arc<Mutex<KP>>> {
     where
         for<'a> P: Deserialize<'a> + std::fmt::Debug,
     {
-        let unverified_token =
-            UnverifiedToken::<P>::validate(token_string, self.check_expiration, &self.client_id)?;
-        unverified_token.verify_async(&self.key_provider).await
+        self.verify_with_policy_async(&self.policy(), token_string, |token, _| Ok(token))
+            .await
+    }
+
+    /// Verify a token and apply the checks of a preset client, before its nonce and replay
+    /// cache entry are consumed.
+    pub(crate) async fn verify_token_with_check_async<P>(
+        &self,
+        token_string: &str,
+        check: impl FnOnce(Token<P>) -> Result<Token<P>, Error>,
+    ) -> Result<Token<P>, Error>
+    where
+        for<'a> P: Deserialize<'a> + std::fmt::Debug,
+    {
+        self.verify_with_policy_async(&self.policy(), token_string, |token, _| check(token))
+            .await
+    }
+
+    async fn verify_with_policy_async<P>(
+        &self,
+        policy: &Policy,
+        token_string: &str,
+        check: impl FnOnce(Token<P>, Algorithm) -> Result<Token<P>, Error>,
+    ) -> Result<Token<P>, Error>
+    where
+        for<'a> P: Deserialize<'a> + std::fmt::Debug,
+    {
+        let unverified_token = policy.validate::<P>(token_string)?;
+        let algorithm = unverified_token.algorithm();
+        let token_id = unverified_token.token_id();
+        let token = unverified_token.verify_async(&self.key_provider).await?;
+        policy.check_client(&token)?;
+        self.consume_token(policy, check(token, algorithm)?, &token_id)
     }
 
     pub async fn verify_token_async(&self, token_string: &str) -> Result<Token<()>, Error> {
@@ -147,6 +521,45 @@ impl<KP: AsyncKeyProvider> GenericClient<Arc<Mutex<KP>>> {
         &self,
         token_string: &str,
     ) -> Result<Token<IdPayload>, Error> {
-        self.verify_token_with_payload_async(token_string).await
+        let policy = self.policy();
+        self.verify_with_policy_async(&policy, token_string, |token, _| {
+            policy.check_id_token(token)
+        })
+        .await
+    }
+
+    /// Verify an ID token and check that its `at_hash` claim matches `access_token`.
+    pub async fn verify_id_token_with_access_token_async(
+        &self,
+        token_string: &str,
+        access_token: &str,
+    ) -> Result<Token<IdPayload>, Error> {
+        self.verify_id_token_with_hash_async(token_string, HashClaim::AccessToken, access_token)
+            .await
+    }
+
+    /// Verify an ID token and check that its `c_hash` claim matches the authorization `code`.
+    pub async fn verify_id_token_with_code_async(
+        &self,
+        token_string: &str,
+        code: &str,
+    ) -> Result<Token<IdPayload>, Error> {
+        self.verify_id_token_with_hash_async(token_string, HashClaim::Code, code)
+            .await
+    }
+
+    async fn verify_id_token_with_hash_async(
+        &self,
+        token_string: &str,
+        claim: HashClaim,
+        value: &str,
+    ) -> Result<Token<IdPayload>, Error> {
+        let policy = self.policy();
+        self.verify_with_policy_async(&policy, token_string, |token, algorithm| {
+            let token = policy.check_id_token(token)?;
+            Self::check_hash(&token, algorithm, claim, value)?;
+            Ok(token)
+        })
+        .await
     }
 }
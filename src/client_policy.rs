@@ -0,0 +1,69 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_derive::Deserialize;
+
+use crate::email_policy::EmailPolicy;
+use crate::error::{Error, InvalidError::InvalidClaims};
+use crate::token::RequiredClaims;
+
+/// Rules for the tokens of one of several OAuth client IDs served by the same client.
+///
+/// Unset rules fall back to those of the client's builder, except that a [ClientPolicy]
+/// without an [EmailPolicy] accepts any email.
+#[derive(Clone, Debug, Default)]
+pub struct ClientPolicy {
+    pub(crate) issuers: Option<Vec<String>>,
+    hosted_domains: Vec<String>,
+    pub(crate) email_policy: Option<EmailPolicy>,
+    max_age: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+struct HostedDomainClaim {
+    hd: Option<String>,
+}
+
+impl ClientPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Accept tokens from these issuers instead of those of the builder.
+    pub fn issuers(mut self, issuers: &[&str]) -> Self {
+        self.issuers = Some(issuers.iter().map(|iss| iss.to_string()).collect());
+        self
+    }
+    /// Require the `hd` claim of a Google Workspace domain. May be called more than once.
+    pub fn hosted_domain(mut self, domain: &str) -> Self {
+        self.hosted_domains.push(domain.to_lowercase());
+        self
+    }
+    /// Apply an [EmailPolicy] to the tokens returned by `verify_id_token`.
+    pub fn email_policy(mut self, policy: EmailPolicy) -> Self {
+        self.email_policy = Some(policy);
+        self
+    }
+    /// Reject tokens issued longer than `max_age` ago, even if they have not expired.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub(crate) fn check(&self, claims: &RequiredClaims, raw_payload: &str) -> Result<(), Error> {
+        if let Some(max_age) = self.max_age {
+            let current_timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            if claims.issued_at.saturating_add(max_age.as_secs()) < current_timestamp {
+                return Err(Error::TooOld);
+            }
+        }
+        if !self.hosted_domains.is_empty() {
+            let HostedDomainClaim { hd } = serde_json::from_str(raw_payload)?;
+            if !hd.is_some_and(|hd| self.hosted_domains.contains(&hd.to_lowercase())) {
+                return Err(Error::InvalidToken(InvalidClaims("hd".to_string())));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use crate::error::KeyProviderError;
+use crate::jwk::JsonWebKey;
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+
+/// Looks up keys in `primary`, and in `fallback` only when `primary` fails, e.g. to use a
+/// [FileKeyProvider](crate::FileKeyProvider) snapshot while the issuer is unreachable.
+///
+/// A key that `primary` does not have is not looked up in `fallback`.
+/// Providers can be nested to chain more than two sources.
+pub struct FallbackProvider<P, F> {
+    primary: P,
+    fallback: F,
+}
+
+impl<P, F> FallbackProvider<P, F> {
+    pub fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<P: KeyProvider, F: KeyProvider> KeyProvider for FallbackProvider<P, F> {
+    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        match self.primary.get_key(key_id) {
+            Ok(key) => Ok(key),
+            Err(e) => fallback(e, self.fallback.get_key(key_id)),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<P: AsyncKeyProvider, F: AsyncKeyProvider> AsyncKeyProvider for FallbackProvider<P, F> {
+    async fn get_key_async(
+        &mut self,
+        key_id: &str,
+    ) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        match self.primary.get_key_async(key_id).await {
+            Ok(key) => Ok(key),
+            Err(e) => fallback(e, self.fallback.get_key_async(key_id).await),
+        }
+    }
+}
+
+fn fallback(
+    primary_error: KeyProviderError,
+    fallback: Result<Option<JsonWebKey>, KeyProviderError>,
+) -> Result<Option<JsonWebKey>, KeyProviderError> {
+    match fallback {
+        Ok(key) => {
+            log::warn!("using the fallback key provider: {primary_error}");
+            Ok(key)
+        }
+        Err(e) => Err(sources(vec![primary_error, e])),
+    }
+}
+
+/// Serves the keys of both providers, as if their key sets were merged, e.g. to accept
+/// tokens of Google and of a staging issuer. A key ID found in `first` is not looked up
+/// in `second`.
+///
+/// When one provider fails, keys of the other are still found.
+/// Providers can be nested to merge more than two sources.
+pub struct MergedProvider<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> MergedProvider<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<A: KeyProvider, B: KeyProvider> KeyProvider for MergedProvider<A, B> {
+    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        match self.first.get_key(key_id) {
+            Ok(Some(key)) => Ok(Some(key)),
+            first => merge(first, self.second.get_key(key_id)),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<A: AsyncKeyProvider, B: AsyncKeyProvider> AsyncKeyProvider for MergedProvider<A, B> {
+    async fn get_key_async(
+        &mut self,
+        key_id: &str,
+    ) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        match self.first.get_key_async(key_id).await {
+            Ok(Some(key)) => Ok(Some(key)),
+            first => merge(first, self.second.get_key_async(key_id).await),
+        }
+    }
+}
+
+/// The key of `second` when `first` has none. Not finding a key is only an error if a
+/// provider that might have had it failed.
+fn merge(
+    first: Result<Option<JsonWebKey>, KeyProviderError>,
+    second: Result<Option<JsonWebKey>, KeyProviderError>,
+) -> Result<Option<JsonWebKey>, KeyProviderError> {
+    match (first, second) {
+        (Err(e), Ok(Some(key))) => {
+            log::warn!("using the keys of the second merged provider: {e}");
+            Ok(Some(key))
+        }
+        (Ok(_), second) => second.map_err(|e| sources(vec![e])),
+        (Err(e), Ok(None)) => Err(sources(vec![e])),
+        (Err(first), Err(second)) => Err(sources(vec![first, second])),
+    }
+}
+
+/// Nested composite providers report the errors of all their sources in one list.
+fn sources(errors: Vec<KeyProviderError>) -> KeyProviderError {
+    KeyProviderError::Sources(
+        errors
+            .into_iter()
+            .flat_map(|e| match e {
+                KeyProviderError::Sources(errors) => errors,
+                e => vec![e],
+            })
+            .collect(),
+    )
+}
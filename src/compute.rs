@@ -0,0 +1,146 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+use serde_derive::Deserialize;
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::error::{Error, InvalidError::InvalidClaims};
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+use crate::key_provider::GoogleKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::token::Token;
+
+#[cfg(feature = "blocking")]
+pub type InstanceIdentityClient = GenericInstanceIdentityClient<RefCell<GoogleKeyProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioInstanceIdentityClient = GenericInstanceIdentityClient<Arc<Mutex<GoogleKeyProvider>>>;
+
+// https://cloud.google.com/compute/docs/instances/verifying-instance-identity#payload
+#[derive(Deserialize, Clone, Debug)]
+pub struct InstanceIdentityPayload {
+    /// The instance's service account
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub google: InstanceIdentityGoogleClaims,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct InstanceIdentityGoogleClaims {
+    pub compute_engine: ComputeEngineClaims,
+}
+
+/// Claims of tokens requested with `format=full`
+#[derive(Deserialize, Clone, Debug)]
+pub struct ComputeEngineClaims {
+    pub project_id: String,
+    pub project_number: u64,
+    pub zone: String,
+    pub instance_id: String,
+    pub instance_name: String,
+    pub instance_creation_timestamp: u64,
+    /// Licenses of the instance's boot disk image, if `licenses=TRUE` was requested
+    #[serde(default)]
+    pub license_id: Vec<String>,
+}
+
+/// Verifies the identity tokens that Compute Engine instances fetch from the metadata server.
+pub struct GenericInstanceIdentityClient<KP> {
+    client: GenericClient<KP>,
+    projects: Vec<String>,
+    zones: Vec<String>,
+}
+
+impl<KP> From<GenericClient<KP>> for GenericInstanceIdentityClient<KP> {
+    fn from(client: GenericClient<KP>) -> Self {
+        Self {
+            client,
+            projects: vec![],
+            zones: vec![],
+        }
+    }
+}
+
+impl<KP> GenericInstanceIdentityClient<KP> {
+    /// Only accept instances of these project IDs.
+    pub fn allow_projects(mut self, project_ids: &[&str]) -> Self {
+        self.projects
+            .extend(project_ids.iter().map(|project| project.to_string()));
+        self
+    }
+
+    /// Only accept instances in these zones, e.g. `us-central1-a`.
+    pub fn allow_zones(mut self, zones: &[&str]) -> Self {
+        self.zones.extend(zones.iter().map(|zone| zone.to_string()));
+        self
+    }
+
+    fn check_instance(
+        &self,
+        token: Token<InstanceIdentityPayload>,
+    ) -> Result<Token<InstanceIdentityPayload>, Error> {
+        let instance = &token.payload.google.compute_engine;
+        if !self.projects.is_empty() && !self.projects.contains(&instance.project_id) {
+            return Err(Error::InvalidToken(InvalidClaims(
+                "google.compute_engine.project_id".to_string(),
+            )));
+        }
+        if !self.zones.is_empty() && !self.zones.contains(&instance.zone) {
+            return Err(Error::InvalidToken(InvalidClaims(
+                "google.compute_engine.zone".to_string(),
+            )));
+        }
+        Ok(token)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl InstanceIdentityClient {
+    /// A [GenericClientBuilder] for tokens requested with `audience`.
+    pub fn builder(audience: &str) -> GenericClientBuilder<RefCell<GoogleKeyProvider>> {
+        GenericClientBuilder::<RefCell<GoogleKeyProvider>>::new(audience)
+    }
+    pub fn new(audience: &str) -> Self {
+        Self::from(Self::builder(audience).build())
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioInstanceIdentityClient {
+    /// A [GenericClientBuilder] for tokens requested with `audience`.
+    pub fn builder(audience: &str) -> GenericClientBuilder<Arc<Mutex<GoogleKeyProvider>>> {
+        GenericClientBuilder::<Arc<Mutex<GoogleKeyProvider>>>::new(audience)
+    }
+    pub fn new(audience: &str) -> Self {
+        Self::from(Self::builder(audience).build())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> GenericInstanceIdentityClient<RefCell<KP>> {
+    pub fn verify_instance_identity(
+        &self,
+        token_string: &str,
+    ) -> Result<Token<InstanceIdentityPayload>, Error> {
+        self.client
+            .verify_token_with_check(token_string, |token| self.check_instance(token))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> GenericInstanceIdentityClient<Arc<Mutex<KP>>> {
+    pub async fn verify_instance_identity_async(
+        &self,
+        token_string: &str,
+    ) -> Result<Token<InstanceIdentityPayload>, Error> {
+        self.client
+            .verify_token_with_check_async(token_string, |token| self.check_instance(token))
+            .await
+    }
+}
@@ -0,0 +1,314 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::Path;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_derive::Deserialize;
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::client_policy::ClientPolicy;
+use crate::email_policy::EmailPolicy;
+use crate::error::{ConfigError, Error};
+use crate::replay::LruReplayCache;
+
+const ENV_PREFIX: &str = "GOOGLE_JWT_";
+
+/// Settings of a [GenericClient] that usually differ between deployments.
+///
+/// Load it with [VerifierConfig::from_file] or [VerifierConfig::from_env], which also check
+/// that it is consistent, then create the client with `Client::from_config`
+/// or `TokioClient::from_config`.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerifierConfig {
+    pub client_id: String,
+    /// Google's sign-in issuers if empty
+    pub issuers: Vec<String>,
+    /// Email domains accepted by `verify_id_token`; any email is accepted if both this and
+    /// `allowed_emails` are empty
+    pub allowed_domains: Vec<String>,
+    pub allowed_emails: Vec<String>,
+    pub allow_unverified_email: bool,
+    /// Clock skew tolerated when checking `exp` and `nbf`
+    pub leeway_secs: u64,
+    /// Reject replayed tokens, remembering at most this many. This is the only configurable
+    /// cache bound: keys are cached for as long as their Cache-Control header allows.
+    pub replay_cache_capacity: Option<usize>,
+    /// Other client IDs accepted by the same client
+    pub clients: HashMap<String, ClientConfig>,
+}
+
+/// Settings of one of the other client IDs of a [VerifierConfig], see [ClientPolicy]
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    /// The issuers of the [VerifierConfig] if empty
+    pub issuers: Vec<String>,
+    pub hosted_domains: Vec<String>,
+    pub allowed_domains: Vec<String>,
+    pub allowed_emails: Vec<String>,
+    pub allow_unverified_email: bool,
+    pub max_age_secs: Option<u64>,
+}
+
+impl VerifierConfig {
+    /// Read a `.toml` or `.json` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Io(format!("{path:?}: {e}")))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&text),
+            Some("json") => Self::from_json(&text),
+            _ => Err(ConfigError::Parse(format!("{path:?} is neither .toml nor .json")).into()),
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, Error> {
+        let config: Self = toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        let config: Self =
+            serde_json::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read `GOOGLE_JWT_CLIENT_ID`, `GOOGLE_JWT_ISSUERS`, `GOOGLE_JWT_ALLOWED_DOMAINS`,
+    /// `GOOGLE_JWT_ALLOWED_EMAILS`, `GOOGLE_JWT_ALLOW_UNVERIFIED_EMAIL`,
+    /// `GOOGLE_JWT_LEEWAY_SECS` and `GOOGLE_JWT_REPLAY_CACHE_CAPACITY`.
+    /// Lists are comma separated. Other client IDs can only be configured in a file.
+    pub fn from_env() -> Result<Self, Error> {
+        Self::from_vars(std::env::vars_os())
+    }
+
+    /// Other variables are ignored even if they are not valid unicode.
+    pub(crate) fn from_vars(
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for (name, value) in vars {
+            let Some(key) = name.to_str().and_then(|name| name.strip_prefix(ENV_PREFIX)) else {
+                continue;
+            };
+            let name = name.to_string_lossy();
+            let value = value
+                .into_string()
+                .map_err(|_| ConfigError::Parse(format!("{name} is not valid unicode")))?;
+            let invalid = || ConfigError::Parse(format!("{name}={value}"));
+            match key {
+                "CLIENT_ID" => config.client_id = value.trim().to_owned(),
+                "ISSUERS" => config.issuers = split_list(&value),
+                "ALLOWED_DOMAINS" => config.allowed_domains = split_list(&value),
+                "ALLOWED_EMAILS" => config.allowed_emails = split_list(&value),
+                "ALLOW_UNVERIFIED_EMAIL" => {
+                    config.allow_unverified_email = value.trim().parse().map_err(|_| invalid())?
+                }
+                "LEEWAY_SECS" => {
+                    config.leeway_secs = value.trim().parse().map_err(|_| invalid())?
+                }
+                "REPLAY_CACHE_CAPACITY" => {
+                    config.replay_cache_capacity =
+                        Some(value.trim().parse().map_err(|_| invalid())?)
+                }
+                _ => return Err(ConfigError::Invalid(format!("unknown variable {name}")).into()),
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the settings that deserialization alone does not.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.client_id.trim().is_empty() {
+            return Err(invalid("client_id is empty"));
+        }
+        check_issuers(&self.issuers)?;
+        check_email_rules(
+            &self.allowed_domains,
+            &self.allowed_emails,
+            self.allow_unverified_email,
+        )?;
+        if self.replay_cache_capacity == Some(0) {
+            return Err(invalid("replay_cache_capacity is 0"));
+        }
+        for (client_id, client) in &self.clients {
+            if client_id.trim().is_empty() || *client_id == self.client_id {
+                return Err(invalid(&format!(
+                    "clients has a duplicate or empty client ID {client_id:?}"
+                )));
+            }
+            check_issuers(&client.issuers)?;
+            check_domains("hosted_domains", &client.hosted_domains)?;
+            check_email_rules(
+                &client.allowed_domains,
+                &client.allowed_emails,
+                client.allow_unverified_email,
+            )?;
+            if client.max_age_secs == Some(0) {
+                return Err(invalid(&format!("max_age_secs of {client_id} is 0")));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn configure<KP>(
+        &self,
+        mut builder: GenericClientBuilder<KP>,
+    ) -> GenericClientBuilder<KP> {
+        builder = builder.before_config();
+        if !self.issuers.is_empty() {
+            builder = builder.issuers(&as_strs(&self.issuers));
+        }
+        if let Some(policy) = email_policy(
+            &self.allowed_domains,
+            &self.allowed_emails,
+            self.allow_unverified_email,
+        ) {
+            builder = builder.email_policy(policy);
+        }
+        if let Some(capacity) = self.replay_cache_capacity {
+            builder = builder.replay_cache(std::sync::Arc::new(LruReplayCache::new(capacity)));
+        }
+        for (client_id, client) in &self.clients {
+            builder = builder.client(client_id, client.policy());
+        }
+        builder.leeway(Duration::from_secs(self.leeway_secs))
+    }
+}
+
+impl ClientConfig {
+    fn policy(&self) -> ClientPolicy {
+        let mut policy = ClientPolicy::new();
+        if !self.issuers.is_empty() {
+            policy = policy.issuers(&as_strs(&self.issuers));
+        }
+        for domain in &self.hosted_domains {
+            policy = policy.hosted_domain(domain);
+        }
+        if let Some(email_policy) = email_policy(
+            &self.allowed_domains,
+            &self.allowed_emails,
+            self.allow_unverified_email,
+        ) {
+            policy = policy.email_policy(email_policy);
+        }
+        match self.max_age_secs {
+            Some(secs) => policy.max_age(Duration::from_secs(secs)),
+            None => policy,
+        }
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn as_strs(values: &[String]) -> Vec<&str> {
+    values.iter().map(String::as_str).collect()
+}
+
+fn invalid(message: &str) -> Error {
+    ConfigError::Invalid(message.to_owned()).into()
+}
+
+fn check_issuers(issuers: &[String]) -> Result<(), Error> {
+    match issuers.iter().any(|iss| iss.trim().is_empty()) {
+        true => Err(invalid("issuers has an empty entry")),
+        false => Ok(()),
+    }
+}
+
+fn check_domains(name: &str, domains: &[String]) -> Result<(), Error> {
+    match domains
+        .iter()
+        .find(|d| d.trim().is_empty() || d.contains('@'))
+    {
+        Some(domain) => Err(invalid(&format!("{name} has an invalid domain {domain:?}"))),
+        None => Ok(()),
+    }
+}
+
+fn check_email_rules(
+    domains: &[String],
+    emails: &[String],
+    allow_unverified: bool,
+) -> Result<(), Error> {
+    check_domains("allowed_domains", domains)?;
+    if let Some(email) = emails.iter().find(|e| !e.contains('@')) {
+        return Err(invalid(&format!(
+            "allowed_emails has an invalid email {email:?}"
+        )));
+    }
+    if allow_unverified && domains.is_empty() && emails.is_empty() {
+        return Err(invalid(
+            "allow_unverified_email has no effect without allowed_domains or allowed_emails",
+        ));
+    }
+    Ok(())
+}
+
+/// No policy, and so no email checks, unless an allow list is configured
+fn email_policy(
+    domains: &[String],
+    emails: &[String],
+    allow_unverified: bool,
+) -> Option<EmailPolicy> {
+    if domains.is_empty() && emails.is_empty() {
+        return None;
+    }
+    let policy = match allow_unverified {
+        true => EmailPolicy::new().allow_unverified(),
+        false => EmailPolicy::new(),
+    };
+    let policy = domains
+        .iter()
+        .fold(policy, |policy, domain| policy.allow_domain(domain));
+    Some(
+        emails
+            .iter()
+            .fold(policy, |policy, email| policy.allow_email(email)),
+    )
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: Default> GenericClientBuilder<RefCell<KP>> {
+    pub fn from_config(config: &VerifierConfig) -> Self {
+        config.configure(Self::new(&config.client_id))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: Default> GenericClientBuilder<Arc<Mutex<KP>>> {
+    pub fn from_config(config: &VerifierConfig) -> Self {
+        config.configure(Self::new(&config.client_id))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: Default> GenericClient<RefCell<KP>> {
+    pub fn from_config(config: &VerifierConfig) -> Self {
+        config.configure(Self::builder(&config.client_id)).build()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: Default> GenericClient<Arc<Mutex<KP>>> {
+    pub fn from_config(config: &VerifierConfig) -> Self {
+        config.configure(Self::builder(&config.client_id)).build()
+    }
+}
@@ -0,0 +1,235 @@
+use crate::error::{Error, InvalidError::InvalidClaims};
+use crate::token::IdPayload;
+
+const GMAIL_DOMAINS: [&str; 2] = ["gmail.com", "googlemail.com"];
+
+/// Rules applied to the `email` claim of a verified ID token.
+///
+/// Deny lists take precedence over allow lists. When no allow list is configured,
+/// every address that is not denied is accepted.
+#[derive(Clone, Debug)]
+pub struct EmailPolicy {
+    require_verified: bool,
+    normalize_gmail: bool,
+    // reject addresses that are not allowed, even if no allow list is configured
+    deny_unlisted: bool,
+    allowed_emails: Vec<String>,
+    allowed_patterns: Vec<String>,
+    denied_emails: Vec<String>,
+    allowed_domains: Vec<String>,
+    denied_domains: Vec<String>,
+}
+
+impl Default for EmailPolicy {
+    fn default() -> Self {
+        Self {
+            require_verified: true,
+            normalize_gmail: false,
+            deny_unlisted: false,
+            allowed_emails: vec![],
+            allowed_patterns: vec![],
+            denied_emails: vec![],
+            allowed_domains: vec![],
+            denied_domains: vec![],
+        }
+    }
+}
+
+impl EmailPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Accept tokens where `email_verified` is missing or false.
+    pub fn allow_unverified(mut self) -> Self {
+        self.require_verified = false;
+        self
+    }
+    /// Strip dots and `+tags` from gmail.com / googlemail.com addresses before matching.
+    pub fn normalize_gmail(mut self) -> Self {
+        self.normalize_gmail = true;
+        self
+    }
+    /// Only accept addresses that are explicitly allowed, so that empty allow lists reject
+    /// every address.
+    pub(crate) fn deny_unlisted(mut self) -> Self {
+        self.deny_unlisted = true;
+        self
+    }
+    pub fn allow_email(mut self, email: &str) -> Self {
+        self.allowed_emails.push(email.to_lowercase());
+        self
+    }
+    /// Allow addresses matching a glob pattern, where `*` matches any run of characters
+    /// and `?` a single one, e.g. `*@my-project.iam.gserviceaccount.com`.
+    pub fn allow_email_pattern(mut self, pattern: &str) -> Self {
+        self.allowed_patterns.push(pattern.to_lowercase());
+        self
+    }
+    pub fn deny_email(mut self, email: &str) -> Self {
+        self.denied_emails.push(email.to_lowercase());
+        self
+    }
+    pub fn allow_domain(mut self, domain: &str) -> Self {
+        self.allowed_domains.push(domain.to_lowercase());
+        self
+    }
+    pub fn deny_domain(mut self, domain: &str) -> Self {
+        self.denied_domains.push(domain.to_lowercase());
+        self
+    }
+
+    pub fn check(&self, payload: &IdPayload) -> Result<(), Error> {
+        let email = payload
+            .email
+            .as_deref()
+            .ok_or(Error::InvalidToken(InvalidClaims("email".to_string())))?;
+        if self.require_verified && payload.email_verified != Some(true) {
+            return Err(Error::EmailNotVerified);
+        }
+        let email = self.canonical(email);
+        let domain = email.rsplit_once('@').map_or("", |(_, domain)| domain);
+        let matches_email = |list: &[String]| list.iter().any(|e| self.canonical(e) == email);
+        let matches_domain =
+            |list: &[String]| list.iter().any(|d| self.canonical_domain(d) == domain);
+
+        if matches_email(&self.denied_emails) || matches_domain(&self.denied_domains) {
+            return Err(Error::EmailNotAllowed);
+        }
+        if !self.deny_unlisted
+            && self.allowed_emails.is_empty()
+            && self.allowed_patterns.is_empty()
+            && self.allowed_domains.is_empty()
+        {
+            return Ok(());
+        }
+        if matches_email(&self.allowed_emails)
+            || self.allowed_patterns.iter().any(|p| glob_match(p, &email))
+            || matches_domain(&self.allowed_domains)
+        {
+            Ok(())
+        } else {
+            Err(Error::EmailNotAllowed)
+        }
+    }
+
+    fn canonical(&self, email: &str) -> String {
+        let email = email.to_lowercase();
+        match email.rsplit_once('@') {
+            Some((local, domain)) if self.normalize_gmail && GMAIL_DOMAINS.contains(&domain) => {
+                let local = local.split_once('+').map_or(local, |(l, _)| l);
+                format!("{}@gmail.com", local.replace('.', ""))
+            }
+            _ => email,
+        }
+    }
+
+    /// The domain of [EmailPolicy::canonical] addresses, so that listed domains match them.
+    fn canonical_domain<'a>(&self, domain: &'a str) -> &'a str {
+        if self.normalize_gmail && GMAIL_DOMAINS.contains(&domain) {
+            "gmail.com"
+        } else {
+            domain
+        }
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` in the pattern, and of the text it was matched against
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[test]
+fn test_glob_match() {
+    let pattern = "*@my-project.iam.gserviceaccount.com";
+    assert!(glob_match(
+        pattern,
+        "tasks@my-project.iam.gserviceaccount.com"
+    ));
+    assert!(!glob_match(
+        pattern,
+        "tasks@other-project.iam.gserviceaccount.com"
+    ));
+    assert!(!glob_match(
+        pattern,
+        "tasks@my-project.iam.gserviceaccount.com.evil"
+    ));
+    assert!(glob_match("a?c*", "abcdef"));
+    assert!(glob_match("*b*d", "abcbd"));
+    assert!(!glob_match("a?c", "ac"));
+}
+
+#[test]
+fn test_gmail_normalization() {
+    let policy = EmailPolicy::new().normalize_gmail();
+    assert_eq!(
+        policy.canonical("Dan.James+x@GoogleMail.com"),
+        "danjames@gmail.com"
+    );
+    assert_eq!(
+        policy.canonical("dan.james+x@example.com"),
+        "dan.james+x@example.com"
+    );
+    let policy = EmailPolicy::new();
+    assert_eq!(
+        policy.canonical("Dan.James@gmail.com"),
+        "dan.james@gmail.com"
+    );
+}
+
+#[test]
+fn test_gmail_domains() {
+    let payload: IdPayload = serde_json::from_value(serde_json::json!({
+        "email": "dan.james@googlemail.com",
+        "email_verified": true,
+    }))
+    .unwrap();
+    let policy = EmailPolicy::new().normalize_gmail();
+    assert!(
+        policy
+            .clone()
+            .allow_domain("googlemail.com")
+            .check(&payload)
+            .is_ok()
+    );
+    assert!(
+        policy
+            .clone()
+            .allow_domain("gmail.com")
+            .check(&payload)
+            .is_ok()
+    );
+    assert_eq!(
+        policy.deny_domain("googlemail.com").check(&payload),
+        Err(Error::EmailNotAllowed)
+    );
+    assert!(
+        EmailPolicy::new()
+            .allow_domain("googlemail.com")
+            .check(&payload)
+            .is_ok()
+    );
+}
@@ -16,18 +16,86 @@ pub enum InvalidError {
     InvalidClaims(String),
     #[error("invalid JWT key id")]
     InvalidKeyId,
+    #[error("missing token claim")]
+    MissingClaim(String),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum NonceError {
+    #[error("token has no nonce")]
+    Missing,
+    #[error("nonce was not issued by this store")]
+    Unknown,
+    #[error("nonce expired")]
+    Expired,
+    #[error("nonce was already used")]
+    Used,
+    #[error("unable to generate a nonce")]
+    Unavailable,
+}
+
+/// Why a key provider could not look up a key
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum KeyProviderError {
+    #[error("unable to fetch {0}: {1}")]
+    Fetch(String, String),
+    #[error("unable to parse {0}: {1}")]
+    Parse(String, String),
+    #[error("unable to read {0}: {1}")]
+    Io(String, String),
+    #[error("discovery document of {0} is for issuer {1}")]
+    IssuerMismatch(String, String),
+    /// The errors of each source of a composite provider, in order
+    #[error("no key source succeeded: {}", join(.0))]
+    Sources(Vec<KeyProviderError>),
+}
+
+fn join(errors: &[KeyProviderError]) -> String {
+    let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    errors.join("; ")
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error("unable to read configuration: {0}")]
+    Io(String),
+    #[error("unable to parse configuration: {0}")]
+    Parse(String),
+    #[error("invalid configuration: {0}")]
+    Invalid(String),
 }
 
 #[derive(Error, Debug, PartialEq)]
 pub enum Error {
     #[error("invalid JWT token")]
     InvalidToken(#[from] InvalidError),
-    #[error("unable to fetch token signing keys")]
-    RetrieveKeyFailure,
-    #[error("verification algorithm is {0:?} (not RS256)")]
+    /// A unit variant in 0.5, match it with `RetrieveKeyFailure(_)`
+    #[error("unable to fetch token signing keys: {0}")]
+    RetrieveKeyFailure(KeyProviderError),
+    #[error("verification algorithm {0:?} is not supported")]
     UnsupportedAlgorithm(Algorithm),
     #[error("token expired")]
     Expired,
+    #[error("token was issued longer ago than the maximum age")]
+    TooOld,
+    #[error("email address is not verified")]
+    EmailNotVerified,
+    #[error("email address is not allowed")]
+    EmailNotAllowed,
+    #[error("{0} claim does not match")]
+    HashMismatch(String),
+    #[error("invalid nonce")]
+    Nonce(#[from] NonceError),
+    #[error("token was already used")]
+    Replayed,
+    #[error("invalid push message: {0}")]
+    InvalidPushMessage(String),
+    #[error("invalid key: {0}")]
+    InvalidKey(String),
+    #[error("no verifier for issuer {0}")]
+    UnknownIssuer(String),
+    #[error("invalid verifier configuration: {0}")]
+    Config(#[from] ConfigError),
 }
 
 impl From<base64::DecodeError> for Error {
@@ -0,0 +1,254 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_derive::Deserialize;
+use serde_json::{Map, Value};
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::error::{Error, InvalidError::InvalidClaims};
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+use crate::key_provider::JwksKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::nonce::NonceStore;
+use crate::replay::ReplayCache;
+use crate::token::Token;
+
+// JWK form of https://www.googleapis.com/robot/v1/metadata/x509/securetoken@system.gserviceaccount.com
+const FIREBASE_ID_TOKEN_KEYS_URL: &str =
+    "https://www.googleapis.com/service_accounts/v1/jwk/securetoken@system.gserviceaccount.com";
+const FIREBASE_SESSION_COOKIE_KEYS_URL: &str =
+    "https://identitytoolkit.googleapis.com/v1/sessionCookiePublicKeys";
+const ID_TOKEN_ISSUER: &str = "https://securetoken.google.com";
+const SESSION_COOKIE_ISSUER: &str = "https://session.firebase.google.com";
+
+#[cfg(feature = "blocking")]
+pub type FirebaseClient = GenericFirebaseClient<RefCell<JwksKeyProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioFirebaseClient = GenericFirebaseClient<Arc<Mutex<JwksKeyProvider>>>;
+
+// https://firebase.google.com/docs/auth/admin/verify-id-tokens#verify_id_tokens_using_a_third-party_jwt_library
+#[derive(Deserialize, Clone, Debug)]
+pub struct FirebasePayload {
+    pub auth_time: u64,
+    pub user_id: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub phone_number: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub firebase: FirebaseClaims,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct FirebaseClaims {
+    pub sign_in_provider: String,
+    pub tenant: Option<String>,
+    /// Provider name to the user's identifiers with that provider
+    #[serde(default)]
+    pub identities: Map<String, Value>,
+}
+
+/// Verifies Firebase Authentication ID tokens and session cookies for a single project.
+///
+/// Session cookies are signed by a different set of keys, so each kind of token
+/// has its own client and key cache.
+pub struct GenericFirebaseClient<KP> {
+    id_tokens: GenericClient<KP>,
+    session_cookies: GenericClient<KP>,
+}
+
+impl<KP> GenericFirebaseClient<KP> {
+    pub(crate) fn issuers(&self) -> Vec<String> {
+        let mut issuers = self.id_tokens.issuers();
+        issuers.extend(self.session_cookies.issuers());
+        issuers
+    }
+
+    pub(crate) fn is_session_cookie_issuer(&self, issuer: &str) -> bool {
+        self.session_cookies
+            .issuers()
+            .iter()
+            .any(|iss| iss == issuer)
+    }
+}
+
+/// Builds a [GenericFirebaseClient] with the audiences, issuers and keys of Firebase ID tokens
+/// and session cookies.
+///
+/// Email policies and other client IDs do not apply to Firebase tokens, so unlike
+/// [GenericClientBuilder] it has no such options.
+pub struct FirebaseClientBuilder<KP> {
+    id_tokens: GenericClientBuilder<KP>,
+    session_cookies: GenericClientBuilder<KP>,
+}
+
+impl<KP> FirebaseClientBuilder<KP> {
+    fn new(project_id: &str, id_token_keys: KP, session_cookie_keys: KP) -> Self {
+        Self {
+            id_tokens: firebase_builder(project_id, ID_TOKEN_ISSUER, id_token_keys),
+            session_cookies: firebase_builder(
+                project_id,
+                SESSION_COOKIE_ISSUER,
+                session_cookie_keys,
+            ),
+        }
+    }
+    fn map(self, f: impl Fn(GenericClientBuilder<KP>) -> GenericClientBuilder<KP>) -> Self {
+        Self {
+            id_tokens: f(self.id_tokens),
+            session_cookies: f(self.session_cookies),
+        }
+    }
+    pub fn unsafe_ignore_expiration(self) -> Self {
+        self.map(GenericClientBuilder::unsafe_ignore_expiration)
+    }
+    /// Tolerate this much clock skew when checking the `exp` and `nbf` claims.
+    pub fn leeway(self, leeway: Duration) -> Self {
+        self.map(|builder| builder.leeway(leeway))
+    }
+    /// Require every token to carry a `nonce` issued by `store`, and consume it.
+    pub fn nonce_store(self, store: Arc<dyn NonceStore>) -> Self {
+        self.map(|builder| builder.nonce_store(store.clone()))
+    }
+    /// Reject tokens and cookies that were already accepted, until they expire.
+    pub fn replay_cache(self, cache: Arc<dyn ReplayCache>) -> Self {
+        self.map(|builder| builder.replay_cache(cache.clone()))
+    }
+    pub fn build(self) -> GenericFirebaseClient<KP> {
+        GenericFirebaseClient {
+            id_tokens: self.id_tokens.build(),
+            session_cookies: self.session_cookies.build(),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl FirebaseClientBuilder<RefCell<JwksKeyProvider>> {
+    /// Look up the keys of ID tokens and of session cookies in other providers.
+    pub fn custom_key_providers<T>(
+        self,
+        id_token_keys: T,
+        session_cookie_keys: T,
+    ) -> FirebaseClientBuilder<RefCell<T>> {
+        FirebaseClientBuilder {
+            id_tokens: self.id_tokens.custom_key_provider(id_token_keys),
+            session_cookies: self
+                .session_cookies
+                .custom_key_provider(session_cookie_keys),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl FirebaseClientBuilder<Arc<Mutex<JwksKeyProvider>>> {
+    /// Look up the keys of ID tokens and of session cookies in other providers.
+    pub fn custom_key_providers<T>(
+        self,
+        id_token_keys: T,
+        session_cookie_keys: T,
+    ) -> FirebaseClientBuilder<Arc<Mutex<T>>> {
+        FirebaseClientBuilder {
+            id_tokens: self.id_tokens.custom_key_provider(id_token_keys),
+            session_cookies: self
+                .session_cookies
+                .custom_key_provider(session_cookie_keys),
+        }
+    }
+}
+
+fn firebase_builder<KP>(
+    project_id: &str,
+    issuer: &str,
+    key_provider: KP,
+) -> GenericClientBuilder<KP> {
+    GenericClientBuilder::with_key_provider(project_id, key_provider)
+        .issuers(&[&format!("{issuer}/{project_id}")])
+}
+
+#[cfg(feature = "blocking")]
+impl FirebaseClient {
+    pub fn builder(project_id: &str) -> FirebaseClientBuilder<RefCell<JwksKeyProvider>> {
+        FirebaseClientBuilder::new(
+            project_id,
+            RefCell::new(JwksKeyProvider::new(FIREBASE_ID_TOKEN_KEYS_URL)),
+            RefCell::new(JwksKeyProvider::new(FIREBASE_SESSION_COOKIE_KEYS_URL)),
+        )
+    }
+    pub fn new(project_id: &str) -> Self {
+        Self::builder(project_id).build()
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioFirebaseClient {
+    pub fn builder(project_id: &str) -> FirebaseClientBuilder<Arc<Mutex<JwksKeyProvider>>> {
+        FirebaseClientBuilder::new(
+            project_id,
+            Arc::new(Mutex::new(JwksKeyProvider::new(FIREBASE_ID_TOKEN_KEYS_URL))),
+            Arc::new(Mutex::new(JwksKeyProvider::new(
+                FIREBASE_SESSION_COOKIE_KEYS_URL,
+            ))),
+        )
+    }
+    pub fn new(project_id: &str) -> Self {
+        Self::builder(project_id).build()
+    }
+}
+
+fn check_firebase_token(token: Token<FirebasePayload>) -> Result<Token<FirebasePayload>, Error> {
+    let subject = token.claims.subject.as_deref().unwrap_or_default();
+    if subject.is_empty() || subject.len() > 128 {
+        return Err(Error::InvalidToken(InvalidClaims("sub".to_string())));
+    }
+    let current_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    if token.payload.auth_time > current_timestamp {
+        return Err(Error::InvalidToken(InvalidClaims("auth_time".to_string())));
+    }
+    Ok(token)
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> GenericFirebaseClient<RefCell<KP>> {
+    pub fn verify_id_token(&self, token_string: &str) -> Result<Token<FirebasePayload>, Error> {
+        self.id_tokens
+            .verify_token_with_check(token_string, check_firebase_token)
+    }
+
+    /// Verify a session cookie created by the Firebase Admin `createSessionCookie` API.
+    pub fn verify_session_cookie(&self, cookie: &str) -> Result<Token<FirebasePayload>, Error> {
+        self.session_cookies
+            .verify_token_with_check(cookie, check_firebase_token)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> GenericFirebaseClient<Arc<Mutex<KP>>> {
+    pub async fn verify_id_token_async(
+        &self,
+        token_string: &str,
+    ) -> Result<Token<FirebasePayload>, Error> {
+        self.id_tokens
+            .verify_token_with_check_async(token_string, check_firebase_token)
+            .await
+    }
+
+    /// Verify a session cookie created by the Firebase Admin `createSessionCookie` API.
+    pub async fn verify_session_cookie_async(
+        &self,
+        cookie: &str,
+    ) -> Result<Token<FirebasePayload>, Error> {
+        self.session_cookies
+            .verify_token_with_check_async(cookie, check_firebase_token)
+            .await
+    }
+}
@@ -1,7 +1,11 @@
 use serde_derive::{Deserialize, Serialize};
 
+use crate::algorithm::Algorithm;
+
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub struct Header {
     #[serde(rename = "kid")]
     pub key_id: String,
+    #[serde(rename = "alg")]
+    pub algorithm: Algorithm,
 }
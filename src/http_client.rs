@@ -3,27 +3,34 @@ use core::future::Future;
 use std::io::{Error, ErrorKind};
 
 use http::response::Response;
+use http::{HeaderMap, StatusCode};
 
 fn io_error<E: std::fmt::Debug>(msg: &str, cause: E) -> Error {
     log::warn!("{msg}\n{cause:#?}");
     Error::new(ErrorKind::Other, msg)
 }
 
+/// A 304 answers a conditional request for keys that are still current, so it is no failure.
+fn log_status(url: &str, status: StatusCode) {
+    if status.is_success() {
+        log::trace!("successful GET {url}");
+    } else if status == StatusCode::NOT_MODIFIED {
+        log::trace!("GET {url} not modified");
+    } else {
+        log::warn!("failed GET {url} ({:?})", status.canonical_reason())
+    }
+}
+
 #[cfg(feature = "blocking")]
-pub fn get_blocking(url: &str) -> Result<Response<String>, Error> {
-    match ureq::get(url)
-        .config()
-        .http_status_as_error(false)
-        .build()
-        .call()
-    {
+pub fn get_blocking(url: &str, headers: &HeaderMap) -> Result<Response<String>, Error> {
+    let mut request = ureq::get(url);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    match request.config().http_status_as_error(false).build().call() {
         Ok(response) => {
             let (parts, mut body) = response.into_parts();
-            if parts.status.is_success() {
-                log::trace!("successful GET {url}");
-            } else {
-                log::warn!("failed GET {url} ({:?})", parts.status.canonical_reason())
-            }
+            log_status(url, parts.status);
             let response_str = body
                 .read_to_string()
                 .map_err(|e| io_error("failed to convert response", e))?;
@@ -35,14 +42,17 @@ pub fn get_blocking(url: &str) -> Result<Response<String>, Error> {
 }
 
 #[cfg(feature = "async")]
-pub fn get_async(url: &str) -> impl Future<Output = Result<Response<String>, Error>> {
+pub fn get_async(
+    url: &str,
+    headers: &HeaderMap,
+) -> impl Future<Output = Result<Response<String>, Error>> {
     use bytes::Bytes;
     use http_body_util::{BodyExt, Empty};
     use hyper_util::client::legacy::Client;
     use hyper_util::rt::TokioExecutor;
 
     let _ = rustls::crypto::ring::default_provider().install_default();
-    let url = url.parse::<http::Uri>().unwrap();
+    let url = url.parse::<http::Uri>();
     let https = hyper_rustls::HttpsConnectorBuilder::new()
         .with_native_roots()
         .expect("no native root CA certificates found")
@@ -51,10 +61,15 @@ pub fn get_async(url: &str) -> impl Future<Output = Result<Response<String>, Err
         .build();
 
     let client: Client<_, Empty<Bytes>> = Client::builder(TokioExecutor::new()).build(https);
+    let headers = headers.clone();
 
     async move {
+        // the URL can come from a discovery document, so it is not trusted to be valid
+        let url = url.map_err(|e| io_error("invalid url", e))?;
+        let mut request = http::Request::get(url.clone()).body(Empty::new()).unwrap();
+        *request.headers_mut() = headers;
         let response = client
-            .get(url.clone())
+            .request(request)
             .await
             .map_err(|e| io_error("failed to receive response", e))?;
         let (parts, body) = response.into_parts();
@@ -64,11 +79,7 @@ pub fn get_async(url: &str) -> impl Future<Output = Result<Response<String>, Err
             .map_err(|e| io_error("failed to collect response body", e))?
             .to_bytes();
         let response_str = String::from_utf8_lossy(&body_bytes).into_owned();
-        if parts.status.is_success() {
-            log::trace!("successful GET {url}");
-        } else {
-            log::warn!("failed GET {url} ({:?})", parts.status.canonical_reason())
-        }
+        log_status(&url.to_string(), parts.status);
         Ok(Response::from_parts(parts, response_str))
     }
 }
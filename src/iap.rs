@@ -0,0 +1,133 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+use serde_derive::Deserialize;
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::error::Error;
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+use crate::key_provider::JwksKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::token::Token;
+
+/// The request header carrying the signed IAP assertion
+pub const IAP_ASSERTION_HEADER: &str = "x-goog-iap-jwt-assertion";
+
+const IAP_KEYS_URL: &str = "https://www.gstatic.com/iap/verify/public_key-jwk";
+const IAP_ISSUER: &str = "https://cloud.google.com/iap";
+
+#[cfg(feature = "blocking")]
+pub type IapClient = GenericIapClient<RefCell<JwksKeyProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioIapClient = GenericIapClient<Arc<Mutex<JwksKeyProvider>>>;
+
+// https://cloud.google.com/iap/docs/signed-headers-howto#verifying_the_jwt_payload
+#[derive(Deserialize, Clone, Debug)]
+pub struct IapPayload {
+    pub email: String,
+    #[serde(rename = "hd")]
+    pub domain: Option<String>,
+    pub google: Option<IapGoogleClaims>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct IapGoogleClaims {
+    /// Access levels from Access Context Manager that apply to the request
+    #[serde(default)]
+    pub access_levels: Vec<String>,
+}
+
+/// The audience of assertions for a backend service behind a load balancer
+pub fn backend_service_audience(project_number: &str, backend_service_id: &str) -> String {
+    format!("/projects/{project_number}/global/backendServices/{backend_service_id}")
+}
+
+/// The audience of assertions for an App Engine app
+pub fn app_engine_audience(project_number: &str, project_id: &str) -> String {
+    format!("/projects/{project_number}/apps/{project_id}")
+}
+
+/// Verifies the assertions that Identity-Aware Proxy adds to proxied requests.
+pub struct GenericIapClient<KP> {
+    client: GenericClient<KP>,
+}
+
+impl<KP> From<GenericClient<KP>> for GenericIapClient<KP> {
+    fn from(client: GenericClient<KP>) -> Self {
+        Self { client }
+    }
+}
+
+impl<KP> GenericIapClient<KP> {
+    pub(crate) fn issuers(&self) -> Vec<String> {
+        self.client.issuers()
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl IapClient {
+    /// A [GenericClientBuilder] with the issuer and keys of IAP assertions.
+    pub fn builder(audience: &str) -> GenericClientBuilder<RefCell<JwksKeyProvider>> {
+        let key_provider = RefCell::new(JwksKeyProvider::new(IAP_KEYS_URL));
+        GenericClientBuilder::with_key_provider(audience, key_provider).issuers(&[IAP_ISSUER])
+    }
+    pub fn new(audience: &str) -> Self {
+        Self::from(Self::builder(audience).build())
+    }
+    pub fn for_backend_service(project_number: &str, backend_service_id: &str) -> Self {
+        Self::new(&backend_service_audience(
+            project_number,
+            backend_service_id,
+        ))
+    }
+    pub fn for_app_engine(project_number: &str, project_id: &str) -> Self {
+        Self::new(&app_engine_audience(project_number, project_id))
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioIapClient {
+    /// A [GenericClientBuilder] with the issuer and keys of IAP assertions.
+    pub fn builder(audience: &str) -> GenericClientBuilder<Arc<Mutex<JwksKeyProvider>>> {
+        let key_provider = Arc::new(Mutex::new(JwksKeyProvider::new(IAP_KEYS_URL)));
+        GenericClientBuilder::with_key_provider(audience, key_provider).issuers(&[IAP_ISSUER])
+    }
+    pub fn new(audience: &str) -> Self {
+        Self::from(Self::builder(audience).build())
+    }
+    pub fn for_backend_service(project_number: &str, backend_service_id: &str) -> Self {
+        Self::new(&backend_service_audience(
+            project_number,
+            backend_service_id,
+        ))
+    }
+    pub fn for_app_engine(project_number: &str, project_id: &str) -> Self {
+        Self::new(&app_engine_audience(project_number, project_id))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> GenericIapClient<RefCell<KP>> {
+    /// Verify the value of the [IAP_ASSERTION_HEADER] request header.
+    pub fn verify_assertion(&self, assertion: &str) -> Result<Token<IapPayload>, Error> {
+        self.client.verify_token_with_payload(assertion)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> GenericIapClient<Arc<Mutex<KP>>> {
+    /// Verify the value of the [IAP_ASSERTION_HEADER] request header.
+    pub async fn verify_assertion_async(
+        &self,
+        assertion: &str,
+    ) -> Result<Token<IapPayload>, Error> {
+        self.client.verify_token_with_payload_async(assertion).await
+    }
+}
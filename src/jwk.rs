@@ -1,47 +1,180 @@
 use crate::algorithm::Algorithm;
 use crate::base64_decode;
 use crate::error::Error;
-use serde_derive::Deserialize;
+use ring::signature::{self, UnparsedPublicKey};
+use serde::{Deserialize, Deserializer};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct JsonWebKeySet {
+    #[serde(deserialize_with = "usable_keys")]
     keys: Vec<JsonWebKey>,
 }
 
+/// Skips keys with an unknown algorithm, such as the encryption keys some issuers publish
+/// alongside their signing keys.
+fn usable_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<JsonWebKey>, D::Error> {
+    Ok(Vec::<Value>::deserialize(deserializer)?
+        .into_iter()
+        .filter_map(|key| serde_json::from_value(key).ok())
+        .collect())
+}
+
 impl JsonWebKeySet {
     pub fn get_key(&self, id: &str) -> Option<JsonWebKey> {
         self.keys.iter().find(|key| key.id == id).cloned()
     }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(try_from = "RawJsonWebKey", into = "RawJsonWebKey")]
 pub struct JsonWebKey {
-    #[serde(rename = "alg")]
     algorithm: Algorithm,
+    id: String,
+    parameters: KeyParameters,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawJsonWebKey {
+    #[serde(rename = "alg")]
+    algorithm: Option<Algorithm>,
     #[serde(rename = "kid")]
     id: String,
-    n: String,
-    e: String,
+    #[serde(flatten)]
+    parameters: KeyParameters,
+}
+
+impl TryFrom<RawJsonWebKey> for JsonWebKey {
+    type Error = String;
+
+    /// Keys without an `alg`, such as Microsoft Entra's, use the usual algorithm of their type.
+    fn try_from(raw: RawJsonWebKey) -> Result<Self, Self::Error> {
+        let algorithm = match (raw.algorithm, &raw.parameters) {
+            (Some(algorithm), _) => algorithm,
+            (None, KeyParameters::Rsa { .. }) => Algorithm::RS256,
+            (None, KeyParameters::Ec { crv, .. }) if crv == "P-256" => Algorithm::ES256,
+            (None, KeyParameters::Ec { crv, .. }) if crv == "P-384" => Algorithm::ES384,
+            _ => return Err(format!("no algorithm for key {}", raw.id)),
+        };
+        Ok(Self {
+            algorithm,
+            id: raw.id,
+            parameters: raw.parameters,
+        })
+    }
+}
+
+impl From<JsonWebKey> for RawJsonWebKey {
+    fn from(key: JsonWebKey) -> Self {
+        Self {
+            algorithm: Some(key.algorithm),
+            id: key.id,
+            parameters: key.parameters,
+        }
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc7518#section-6
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kty")]
+enum KeyParameters {
+    #[serde(rename = "RSA")]
+    Rsa { n: String, e: String },
+    #[serde(rename = "EC")]
+    Ec { crv: String, x: String, y: String },
+    #[serde(other)]
+    Unsupported,
 }
 
+// DER SubjectPublicKeyInfo header of an uncompressed P-256 public key
+#[cfg(feature = "play-integrity")]
+const P256_SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
 impl JsonWebKey {
+    /// An ES256 key from the DER encoding of a P-256 public key.
+    #[cfg(feature = "play-integrity")]
+    pub(crate) fn from_p256_der(id: &str, der: &[u8]) -> Option<Self> {
+        let point = der.strip_prefix(&P256_SPKI_PREFIX)?;
+        let (0x04, coordinates) = point.split_first()? else {
+            return None;
+        };
+        if coordinates.len() != 64 {
+            return None;
+        }
+        let (x, y) = coordinates.split_at(32);
+        Some(Self {
+            algorithm: Algorithm::ES256,
+            id: id.to_owned(),
+            parameters: KeyParameters::Ec {
+                crv: "P-256".to_owned(),
+                x: crate::base64_encode(x),
+                y: crate::base64_encode(y),
+            },
+        })
+    }
+
     pub fn get_id(&self) -> String {
         self.id.clone()
     }
 
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
     pub fn verify(&self, body: &[u8], signature: &[u8]) -> Result<(), Error> {
-        match self.algorithm {
-            Algorithm::RS256 => ring::rsa::PublicKeyComponents {
-                n: base64_decode(&self.n)?,
-                e: base64_decode(&self.e)?,
+        match (&self.parameters, self.algorithm) {
+            (
+                KeyParameters::Rsa { n, e },
+                Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512,
+            ) => {
+                let parameters = match self.algorithm {
+                    Algorithm::RS256 => &signature::RSA_PKCS1_2048_8192_SHA256,
+                    Algorithm::RS384 => &signature::RSA_PKCS1_2048_8192_SHA384,
+                    _ => &signature::RSA_PKCS1_2048_8192_SHA512,
+                };
+                signature::RsaPublicKeyComponents {
+                    n: base64_decode(n)?,
+                    e: base64_decode(e)?,
+                }
+                .verify(parameters, body, signature)
+                .map_err(Error::from)
+            }
+            (KeyParameters::Ec { crv, x, y }, Algorithm::ES256 | Algorithm::ES384) => {
+                let parameters = match (self.algorithm, crv.as_str()) {
+                    (Algorithm::ES256, "P-256") => &signature::ECDSA_P256_SHA256_FIXED,
+                    (Algorithm::ES384, "P-384") => &signature::ECDSA_P384_SHA384_FIXED,
+                    _ => return Err(Error::UnsupportedAlgorithm(self.algorithm)),
+                };
+                // uncompressed point encoding: 0x04 || x || y
+                let mut point = vec![0x04];
+                point.extend(base64_decode(x)?);
+                point.extend(base64_decode(y)?);
+                UnparsedPublicKey::new(parameters, point)
+                    .verify(body, signature)
+                    .map_err(Error::from)
             }
-            .verify(
-                &ring::signature::RSA_PKCS1_2048_8192_SHA256,
-                body,
-                signature,
-            )
-            .map_err(Error::from),
             _ => Err(Error::UnsupportedAlgorithm(self.algorithm)),
         }
     }
 }
+
+#[test]
+fn test_key_algorithm() {
+    let keys: JsonWebKeySet = serde_json::from_str(
+        r#"{"keys": [
+            {"kty": "RSA", "use": "sig", "kid": "entra", "n": "AQAB", "e": "AQAB"},
+            {"kty": "RSA", "use": "enc", "kid": "keycloak-enc", "alg": "RSA-OAEP", "n": "AQAB", "e": "AQAB"},
+            {"kty": "EC", "kid": "ec", "crv": "P-384", "x": "AA", "y": "AA"},
+            {"kty": "OKP", "kid": "okp", "crv": "Ed25519", "x": "AA"}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(keys.get_key("entra").unwrap().algorithm(), Algorithm::RS256);
+    assert_eq!(keys.get_key("ec").unwrap().algorithm(), Algorithm::ES384);
+    assert!(keys.get_key("keycloak-enc").is_none());
+    assert!(keys.get_key("okp").is_none());
+}
@@ -1,73 +1,266 @@
-use std::time::Instant;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use cache_control::CacheControl;
-use http::{HeaderMap, header::CACHE_CONTROL};
+use http::header::{CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use http::{HeaderMap, HeaderValue, Response, StatusCode};
+use serde_derive::{Deserialize, Serialize};
 
+use crate::error::KeyProviderError;
 use crate::http_client;
 use crate::jwk::{JsonWebKey, JsonWebKeySet};
+use crate::oidc::OidcProvider;
 
-const GOOGLE_CERT_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+pub const GOOGLE_CERT_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
 
 #[cfg(feature = "blocking")]
 pub trait KeyProvider {
-    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, ()>;
+    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, KeyProviderError>;
 }
 
 #[cfg(feature = "async")]
 #[allow(async_fn_in_trait)]
 pub trait AsyncKeyProvider {
-    async fn get_key_async(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, ()>;
+    async fn get_key_async(&mut self, key_id: &str)
+    -> Result<Option<JsonWebKey>, KeyProviderError>;
 }
 
-pub struct GoogleKeyProvider {
+/// How long a response may be cached. Responses without a max-age are used once
+/// and fetched again on the next lookup.
+pub(crate) fn max_age(headers: &HeaderMap) -> Duration {
+    headers
+        .get(CACHE_CONTROL)
+        .and_then(|hv| hv.to_str().ok())
+        .and_then(CacheControl::from_value)
+        .and_then(|c| c.max_age)
+        .unwrap_or_default()
+}
+
+/// The response to a GET of `url`, if it was successful
+pub(crate) fn successful(
+    url: &str,
+    response: Result<Response<String>, std::io::Error>,
+) -> Result<Response<String>, KeyProviderError> {
+    match response {
+        Ok(response) if response.status().is_success() => Ok(response),
+        Ok(response) => Err(KeyProviderError::Fetch(
+            url.to_owned(),
+            response.status().to_string(),
+        )),
+        Err(e) => Err(KeyProviderError::Fetch(url.to_owned(), e.to_string())),
+    }
+}
+
+/// Fetches a JSON web key set from `url`, caching it according to the Cache-Control header.
+///
+/// Expired keys are revalidated with the `ETag` or `Last-Modified` of the response they came
+/// from, and kept if the key set has not changed.
+pub struct JwksKeyProvider {
+    url: String,
     cached: Option<JsonWebKeySet>,
     expiration_time: Instant,
+    validators: Validators,
+    snapshot_path: Option<PathBuf>,
+}
+
+/// The key provider for Google ID tokens, [OidcProvider::google]
+pub type GoogleKeyProvider = OidcProvider;
+
+// https://www.rfc-editor.org/rfc/rfc9110#section-8.8
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            let value = headers.get(name)?.to_str().ok()?;
+            Some(value.to_owned())
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
 }
 
-impl Default for GoogleKeyProvider {
+/// The cached keys of a [JwksKeyProvider], as written to its snapshot file
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    url: String,
+    keys: JsonWebKeySet,
+    /// Seconds since the Unix epoch, as an [Instant] cannot be stored
+    expires_at: u64,
+    #[serde(flatten)]
+    validators: Validators,
+}
+
+// distinguishes the temporary snapshot files of one process
+static SNAPSHOT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl Default for JwksKeyProvider {
     fn default() -> Self {
+        Self::new(GOOGLE_CERT_URL)
+    }
+}
+
+impl JwksKeyProvider {
+    pub fn new(url: &str) -> Self {
         Self {
+            url: url.to_owned(),
             cached: None,
             expiration_time: Instant::now(),
+            validators: Validators::default(),
+            snapshot_path: None,
         }
     }
-}
-
-impl GoogleKeyProvider {
-    fn process_response(&mut self, headers: &HeaderMap, text: &str) -> Result<&JsonWebKeySet, ()> {
-        if let Some(max_age) = headers
-            .get(CACHE_CONTROL)
-            .and_then(|hv| hv.to_str().ok())
-            .and_then(CacheControl::from_value)
-            .and_then(|c| c.max_age)
-        {
-            self.cached = Some(serde_json::from_str(text).map_err(|_| ())?);
-            self.expiration_time = Instant::now() + max_age;
+    /// Restore the keys saved at `path`, and save them there whenever they are fetched,
+    /// so that a new process does not have to fetch them again.
+    ///
+    /// Expired keys are restored only to be revalidated before their next use. The file is
+    /// replaced atomically, and may be shared by processes using the same URL.
+    pub fn snapshot_path(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        match read_snapshot(&path) {
+            Ok(snapshot) if snapshot.url == self.url => self.restore(snapshot),
+            Ok(snapshot) => log::warn!("key snapshot {path:?} is for {}", snapshot.url),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("unable to read key snapshot {path:?}: {e}"),
         }
-        Ok(self.cached.as_ref().unwrap())
+        self.snapshot_path = Some(path);
+        self
+    }
+    fn restore(&mut self, snapshot: Snapshot) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let remaining = Duration::from_secs(snapshot.expires_at).saturating_sub(now);
+        self.cached = Some(snapshot.keys);
+        // a snapshot too far in the future to represent is treated as expired
+        let restored_at = Instant::now();
+        self.expiration_time = restored_at.checked_add(remaining).unwrap_or(restored_at);
+        self.validators = snapshot.validators;
     }
     #[cfg(feature = "blocking")]
-    pub fn download_keys(&mut self) -> Result<&JsonWebKeySet, ()> {
-        let result = http_client::get_blocking(GOOGLE_CERT_URL)
-            .into_iter()
-            .find(|r| r.status().is_success())
-            .ok_or(())?;
-        self.process_response(result.headers(), result.body())
+    fn save_snapshot(&self) {
+        let Some((path, temporary, contents)) = self.pending_snapshot() else {
+            return;
+        };
+        // renaming a file of the same directory over the snapshot replaces it atomically
+        let result = contents
+            .and_then(|contents| std::fs::write(&temporary, contents))
+            .and_then(|()| {
+                std::fs::rename(&temporary, path).inspect_err(|_| {
+                    let _ = std::fs::remove_file(&temporary);
+                })
+            });
+        if let Err(e) = result {
+            log::warn!("unable to write key snapshot {path:?}: {e}");
+        }
     }
+    /// [JwksKeyProvider::save_snapshot] without blocking the runtime
     #[cfg(feature = "async")]
-    async fn download_keys_async(&mut self) -> Result<&JsonWebKeySet, ()> {
-        let result = http_client::get_async(GOOGLE_CERT_URL)
-            .await
-            .into_iter()
-            .find(|r| r.status().is_success())
-            .ok_or(())?;
-        self.process_response(result.headers(), result.body())
+    async fn save_snapshot_async(&self) {
+        let Some((path, temporary, contents)) = self.pending_snapshot() else {
+            return;
+        };
+        let write = async {
+            tokio::fs::write(&temporary, contents?).await?;
+            tokio::fs::rename(&temporary, path).await
+        };
+        if let Err(e) = write.await {
+            let _ = tokio::fs::remove_file(&temporary).await;
+            log::warn!("unable to write key snapshot {path:?}: {e}");
+        }
+    }
+    /// The snapshot path, the temporary file to write the snapshot to before it is renamed
+    /// over that path, and the contents of the snapshot
+    fn pending_snapshot(&self) -> Option<(&Path, PathBuf, std::io::Result<Vec<u8>>)> {
+        let path = self.snapshot_path.as_deref()?;
+        let keys = self.cached.as_ref()?;
+        let remaining = self
+            .expiration_time
+            .saturating_duration_since(Instant::now());
+        let expires_at = (SystemTime::now() + remaining)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let snapshot = Snapshot {
+            url: self.url.clone(),
+            keys: keys.clone(),
+            expires_at: expires_at.as_secs(),
+            validators: self.validators.clone(),
+        };
+        let mut temporary = path.as_os_str().to_owned();
+        let counter = SNAPSHOT_COUNTER.fetch_add(1, Ordering::Relaxed);
+        temporary.push(format!(".{}.{counter}.tmp", std::process::id()));
+        let contents = serde_json::to_vec(&snapshot).map_err(std::io::Error::from);
+        Some((path, temporary.into(), contents))
+    }
+    /// Headers that let the server answer 304 Not Modified if the cached keys are still current
+    fn request_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if self.cached.is_none() {
+            return headers;
+        }
+        let header = |value: &Option<String>| HeaderValue::from_str(value.as_deref()?).ok();
+        if let Some(etag) = header(&self.validators.etag) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = header(&self.validators.last_modified) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+    /// Update the cached keys, which the caller then saves to the snapshot file
+    fn process_response(
+        &mut self,
+        response: Result<Response<String>, std::io::Error>,
+    ) -> Result<(), KeyProviderError> {
+        let headers = match response {
+            Ok(response)
+                if response.status() == StatusCode::NOT_MODIFIED && self.cached.is_some() =>
+            {
+                response.into_parts().0.headers
+            }
+            response => {
+                let response = successful(&self.url, response)?;
+                let keys = serde_json::from_str(response.body())
+                    .map_err(|e| KeyProviderError::Parse(self.url.clone(), e.to_string()))?;
+                self.cached = Some(keys);
+                self.validators = Validators::from_headers(response.headers());
+                response.into_parts().0.headers
+            }
+        };
+        self.expiration_time = Instant::now() + max_age(&headers);
+        Ok(())
+    }
+    #[cfg(feature = "blocking")]
+    pub fn download_keys(&mut self) -> Result<&JsonWebKeySet, KeyProviderError> {
+        let response = http_client::get_blocking(&self.url, &self.request_headers());
+        self.process_response(response)?;
+        self.save_snapshot();
+        Ok(self.cached.as_ref().unwrap())
+    }
+    #[cfg(feature = "async")]
+    async fn download_keys_async(&mut self) -> Result<&JsonWebKeySet, KeyProviderError> {
+        let response = http_client::get_async(&self.url, &self.request_headers()).await;
+        self.process_response(response)?;
+        self.save_snapshot_async().await;
+        Ok(self.cached.as_ref().unwrap())
     }
 }
 
+fn read_snapshot(path: &Path) -> std::io::Result<Snapshot> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
 #[cfg(feature = "blocking")]
-impl KeyProvider for GoogleKeyProvider {
-    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, ()> {
+impl KeyProvider for JwksKeyProvider {
+    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, KeyProviderError> {
         if let Some(ref cached_keys) = self.cached {
             if self.expiration_time > Instant::now() {
                 return Ok(cached_keys.get_key(key_id));
@@ -78,8 +271,11 @@ impl KeyProvider for GoogleKeyProvider {
 }
 
 #[cfg(feature = "async")]
-impl AsyncKeyProvider for GoogleKeyProvider {
-    async fn get_key_async(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, ()> {
+impl AsyncKeyProvider for JwksKeyProvider {
+    async fn get_key_async(
+        &mut self,
+        key_id: &str,
+    ) -> Result<Option<JsonWebKey>, KeyProviderError> {
         if let Some(ref cached_keys) = self.cached {
             if self.expiration_time > Instant::now() {
                 return Ok(cached_keys.get_key(key_id));
@@ -89,6 +285,175 @@ impl AsyncKeyProvider for GoogleKeyProvider {
     }
 }
 
+/// Serves a fixed set of keys, without any network access, e.g. to pin keys.
+pub struct StaticKeyProvider {
+    keys: JsonWebKeySet,
+}
+
+impl StaticKeyProvider {
+    pub fn new(keys: JsonWebKeySet) -> Self {
+        Self { keys }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl KeyProvider for StaticKeyProvider {
+    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        Ok(self.keys.get_key(key_id))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncKeyProvider for StaticKeyProvider {
+    async fn get_key_async(
+        &mut self,
+        key_id: &str,
+    ) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        Ok(self.keys.get_key(key_id))
+    }
+}
+
+/// Reads a JSON web key set from a file, and reads it again when its modification time
+/// or length changes.
+///
+/// If the file cannot be read or parsed, the keys read before are kept until it can.
+/// [AsyncKeyProvider::get_key_async] reads the file without blocking the runtime.
+pub struct FileKeyProvider {
+    path: PathBuf,
+    cached: Option<JsonWebKeySet>,
+    /// The modification time and length of the file the keys were read from
+    version: Option<(SystemTime, u64)>,
+}
+
+impl FileKeyProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cached: None,
+            version: None,
+        }
+    }
+
+    fn io_error(&self, e: std::io::Error) -> KeyProviderError {
+        KeyProviderError::Io(self.path.display().to_string(), e.to_string())
+    }
+
+    /// The modification time and length of the file, which change when it is rewritten
+    /// unless it keeps its length within the resolution of the file system's timestamps
+    fn version_of(
+        &self,
+        metadata: std::io::Result<Metadata>,
+    ) -> Result<(SystemTime, u64), KeyProviderError> {
+        metadata
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+            .map_err(|e| self.io_error(e))
+    }
+
+    fn parse(&mut self, version: (SystemTime, u64), text: &str) -> Result<(), KeyProviderError> {
+        let keys = serde_json::from_str(text)
+            .map_err(|e| KeyProviderError::Parse(self.path.display().to_string(), e.to_string()))?;
+        self.cached = Some(keys);
+        self.version = Some(version);
+        Ok(())
+    }
+
+    fn keys(
+        &mut self,
+        reloaded: Result<(), KeyProviderError>,
+    ) -> Result<&JsonWebKeySet, KeyProviderError> {
+        if let Err(e) = reloaded {
+            if self.cached.is_none() {
+                return Err(e);
+            }
+            log::warn!("keeping the keys read before: {e}");
+        }
+        Ok(self.cached.as_ref().unwrap())
+    }
+
+    #[cfg(feature = "blocking")]
+    fn reload(&mut self) -> Result<(), KeyProviderError> {
+        let version = self.version_of(std::fs::metadata(&self.path))?;
+        if self.version != Some(version) {
+            let text = std::fs::read_to_string(&self.path).map_err(|e| self.io_error(e))?;
+            self.parse(version, &text)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    async fn reload_async(&mut self) -> Result<(), KeyProviderError> {
+        let version = self.version_of(tokio::fs::metadata(&self.path).await)?;
+        if self.version != Some(version) {
+            let text = tokio::fs::read_to_string(&self.path)
+                .await
+                .map_err(|e| self.io_error(e))?;
+            self.parse(version, &text)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl KeyProvider for FileKeyProvider {
+    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        let reloaded = self.reload();
+        Ok(self.keys(reloaded)?.get_key(key_id))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncKeyProvider for FileKeyProvider {
+    async fn get_key_async(
+        &mut self,
+        key_id: &str,
+    ) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        let reloaded = self.reload_async().await;
+        Ok(self.keys(reloaded)?.get_key(key_id))
+    }
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_key_snapshot() {
+    const URL: &str = "https://issuer.example.com/jwks";
+    let path = std::env::temp_dir().join(format!("jwks-snapshot-{}.json", std::process::id()));
+    let response = |status: StatusCode| {
+        let body =
+            r#"{"keys": [{"kty": "RSA", "kid": "k1", "alg": "RS256", "n": "AQAB", "e": "AQAB"}]}"#;
+        Response::builder()
+            .status(status)
+            .header(CACHE_CONTROL, "public, max-age=3600")
+            .header(ETAG, "\"v1\"")
+            .body(body.to_string())
+            .map_err(std::io::Error::other)
+    };
+
+    let mut provider = JwksKeyProvider::new(URL).snapshot_path(&path);
+    assert!(provider.cached.is_none());
+    assert!(provider.request_headers().is_empty());
+    assert!(provider.process_response(response(StatusCode::OK)).is_ok());
+    provider.save_snapshot();
+
+    let mut restored = JwksKeyProvider::new(URL).snapshot_path(&path);
+    assert!(restored.expiration_time > Instant::now() + Duration::from_secs(3500));
+    assert!(restored.cached.as_ref().unwrap().get_key("k1").is_some());
+    assert_eq!(restored.request_headers()[IF_NONE_MATCH], "\"v1\"");
+
+    // an expired snapshot is revalidated, and kept if the server has nothing newer
+    restored.expiration_time = Instant::now();
+    restored.save_snapshot();
+    let mut expired = JwksKeyProvider::new(URL).snapshot_path(&path);
+    assert!(expired.expiration_time <= Instant::now());
+    let not_modified = response(StatusCode::NOT_MODIFIED).map(|r| r.map(|_| String::new()));
+    assert!(expired.process_response(not_modified).is_ok());
+    assert!(expired.cached.as_ref().unwrap().get_key("k1").is_some());
+    assert!(expired.expiration_time > Instant::now());
+
+    let other = JwksKeyProvider::new("https://other.example.com/jwks").snapshot_path(&path);
+    assert!(other.cached.is_none());
+    std::fs::remove_file(&path).unwrap();
+}
+
 #[cfg(feature = "blocking")]
 #[test]
 pub fn test_google_provider() {
@@ -99,9 +464,27 @@ pub fn test_google_provider() {
 
 #[cfg(all(test, feature = "async"))]
 mod async_test {
-    use super::{AsyncKeyProvider, GoogleKeyProvider};
+    use std::time::{Duration, Instant};
+
+    use super::{AsyncKeyProvider, GoogleKeyProvider, JwksKeyProvider};
     use tokio;
     #[tokio::test]
+    async fn test_key_snapshot_async() {
+        const URL: &str = "https://issuer.example.com/jwks";
+        let path =
+            std::env::temp_dir().join(format!("jwks-snapshot-async-{}.json", std::process::id()));
+        let mut provider = JwksKeyProvider::new(URL).snapshot_path(&path);
+        let keys =
+            r#"{"keys": [{"kty": "RSA", "kid": "k1", "alg": "RS256", "n": "AQAB", "e": "AQAB"}]}"#;
+        provider.cached = Some(serde_json::from_str(keys).unwrap());
+        provider.expiration_time = Instant::now() + Duration::from_secs(3600);
+        provider.save_snapshot_async().await;
+
+        let restored = JwksKeyProvider::new(URL).snapshot_path(&path);
+        assert!(restored.cached.unwrap().get_key("k1").is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[tokio::test]
     async fn test_google_provider_async() {
         let mut provider = GoogleKeyProvider::default();
         assert!(provider.get_key_async("test").await.is_ok());
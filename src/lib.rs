@@ -2,23 +2,137 @@
 mod test;
 
 mod algorithm;
+mod app_check;
+mod apple;
+mod chat;
 mod client;
+mod client_policy;
+mod composite;
+mod compute;
+#[cfg(feature = "config")]
+mod config;
+mod email_policy;
 mod error;
+mod firebase;
 mod header;
 mod http_client;
+mod iap;
 mod jwk;
 mod key_provider;
+mod nonce;
+mod oidc;
+#[cfg(feature = "play-integrity")]
+mod play_integrity;
+mod policy_handle;
+mod pubsub;
+mod registry;
+mod replay;
+mod risc;
 mod token;
 mod unverified_token;
 
+#[cfg(feature = "blocking")]
+pub use crate::app_check::AppCheckClient;
+#[cfg(feature = "async")]
+pub use crate::app_check::TokioAppCheckClient;
+pub use crate::app_check::{AppCheckPayload, GenericAppCheckClient};
+#[cfg(feature = "blocking")]
+pub use crate::apple::AppleClient;
+#[cfg(feature = "async")]
+pub use crate::apple::TokioAppleClient;
+pub use crate::apple::{ApplePayload, GenericAppleClient, RealUserStatus};
+#[cfg(feature = "blocking")]
+pub use crate::chat::ChatClient;
+pub use crate::chat::GenericChatClient;
+#[cfg(feature = "async")]
+pub use crate::chat::TokioChatClient;
 #[cfg(feature = "blocking")]
 pub use crate::client::Client;
 #[cfg(feature = "async")]
 pub use crate::client::TokioClient;
+pub use crate::client_policy::ClientPolicy;
+pub use crate::composite::{FallbackProvider, MergedProvider};
+#[cfg(feature = "blocking")]
+pub use crate::compute::InstanceIdentityClient;
+#[cfg(feature = "async")]
+pub use crate::compute::TokioInstanceIdentityClient;
+pub use crate::compute::{
+    ComputeEngineClaims, GenericInstanceIdentityClient, InstanceIdentityGoogleClaims,
+    InstanceIdentityPayload,
+};
+#[cfg(feature = "config")]
+pub use crate::config::{ClientConfig, VerifierConfig};
+pub use crate::email_policy::EmailPolicy;
+#[cfg(feature = "blocking")]
+pub use crate::firebase::FirebaseClient;
+#[cfg(feature = "async")]
+pub use crate::firebase::TokioFirebaseClient;
+pub use crate::firebase::{
+    FirebaseClaims, FirebaseClientBuilder, FirebasePayload, GenericFirebaseClient,
+};
+#[cfg(feature = "blocking")]
+pub use crate::iap::IapClient;
+#[cfg(feature = "async")]
+pub use crate::iap::TokioIapClient;
+pub use crate::iap::{
+    GenericIapClient, IAP_ASSERTION_HEADER, IapGoogleClaims, IapPayload, app_engine_audience,
+    backend_service_audience,
+};
+pub use crate::jwk::{JsonWebKey, JsonWebKeySet};
+#[cfg(feature = "async")]
+pub use crate::key_provider::AsyncKeyProvider;
+#[cfg(feature = "blocking")]
+pub use crate::key_provider::KeyProvider;
+pub use crate::key_provider::{
+    FileKeyProvider, GoogleKeyProvider, JwksKeyProvider, StaticKeyProvider,
+};
+pub use crate::nonce::{MemoryNonceStore, NonceStore};
+#[cfg(feature = "blocking")]
+pub use crate::oidc::OidcClient;
+#[cfg(feature = "async")]
+pub use crate::oidc::TokioOidcClient;
+pub use crate::oidc::{ENTRA_ISSUER, OidcProvider, ProviderConfiguration};
+#[cfg(feature = "play-integrity")]
+pub use crate::play_integrity::{
+    AccountDetails, AppIntegrity, AppLicensingVerdict, AppRecognitionVerdict, DeviceIntegrity,
+    DeviceRecognitionVerdict, IntegrityPayload, PlayIntegrityVerifier, RequestDetails,
+};
+#[cfg(feature = "config")]
+pub use crate::policy_handle::ConfigWatcher;
+pub use crate::policy_handle::PolicyHandle;
+#[cfg(feature = "blocking")]
+pub use crate::pubsub::PubSubPushVerifier;
+#[cfg(feature = "async")]
+pub use crate::pubsub::TokioPubSubPushVerifier;
+pub use crate::pubsub::{GenericPubSubPushVerifier, PushEnvelope, PushMessage};
+#[cfg(feature = "async")]
+pub use crate::registry::TokioVerifierRegistry;
+#[cfg(feature = "blocking")]
+pub use crate::registry::VerifierRegistry;
+pub use crate::registry::{GenericVerifierRegistry, VerifiedToken};
+pub use crate::replay::{LruReplayCache, ReplayCache};
+#[cfg(feature = "blocking")]
+pub use crate::risc::RiscClient;
+#[cfg(feature = "async")]
+pub use crate::risc::TokioRiscClient;
+pub use crate::risc::{EventSubject, GenericRiscClient, SecurityEvent, SecurityEventPayload};
 pub use crate::token::{IdPayload, RequiredClaims, Token};
-pub use error::Error;
+pub use error::{ConfigError, Error, KeyProviderError, NonceError};
 
 fn base64_decode(input: &str) -> Result<Vec<u8>, base64::DecodeError> {
     use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
     URL_SAFE_NO_PAD.decode(input)
 }
+
+fn base64_encode(input: &[u8]) -> String {
+    use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+    URL_SAFE_NO_PAD.encode(input)
+}
+
+/// The token of an `Authorization` header value, with or without the `Bearer` scheme
+fn bearer_token(authorization: &str) -> &str {
+    match authorization.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.trim(),
+        _ => authorization,
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::base64_encode;
+use crate::error::NonceError;
+
+/// Issues nonces for sign-in requests and accepts each of them exactly once.
+///
+/// Implementations are shared between threads, so state changes go through `&self`.
+pub trait NonceStore: Send + Sync {
+    fn issue(&self) -> Result<String, NonceError>;
+    fn consume(&self, nonce: &str) -> Result<(), NonceError>;
+}
+
+struct IssuedNonce {
+    expiration_time: Instant,
+    used: bool,
+}
+
+/// A [NonceStore] that keeps nonces in memory until they expire.
+///
+/// Expired nonces are dropped whenever a nonce is issued or consumed.
+pub struct MemoryNonceStore {
+    ttl: Duration,
+    rng: SystemRandom,
+    nonces: Mutex<HashMap<String, IssuedNonce>>,
+}
+
+impl MemoryNonceStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            rng: SystemRandom::new(),
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn issue(&self) -> Result<String, NonceError> {
+        let mut bytes = [0u8; 32];
+        self.rng
+            .fill(&mut bytes)
+            .map_err(|_| NonceError::Unavailable)?;
+        let nonce = base64_encode(&bytes);
+        let now = Instant::now();
+        let mut nonces = self.nonces.lock().unwrap();
+        nonces.retain(|_, issued| issued.expiration_time > now);
+        nonces.insert(
+            nonce.clone(),
+            IssuedNonce {
+                expiration_time: now + self.ttl,
+                used: false,
+            },
+        );
+        Ok(nonce)
+    }
+
+    fn consume(&self, nonce: &str) -> Result<(), NonceError> {
+        let now = Instant::now();
+        let mut nonces = self.nonces.lock().unwrap();
+        let issued = nonces.get_mut(nonce).ok_or(NonceError::Unknown)?;
+        let result = if issued.used {
+            Err(NonceError::Used)
+        } else if issued.expiration_time <= now {
+            Err(NonceError::Expired)
+        } else {
+            issued.used = true;
+            Ok(())
+        };
+        nonces.retain(|_, issued| issued.expiration_time > now);
+        result
+    }
+}
+
+#[test]
+fn test_memory_nonce_store() {
+    let store = MemoryNonceStore::new(Duration::from_secs(60));
+    let nonce = store.issue().unwrap();
+    assert_ne!(nonce, store.issue().unwrap());
+    assert_eq!(store.consume("unknown"), Err(NonceError::Unknown));
+    assert_eq!(store.consume(&nonce), Ok(()));
+    assert_eq!(store.consume(&nonce), Err(NonceError::Used));
+
+    let store = MemoryNonceStore::new(Duration::from_millis(20));
+    let nonce = store.issue().unwrap();
+    let other = store.issue().unwrap();
+    std::thread::sleep(Duration::from_millis(40));
+    assert_eq!(store.consume(&nonce), Err(NonceError::Expired));
+    // consuming drops the other expired nonces too
+    assert_eq!(store.consume(&other), Err(NonceError::Unknown));
+    assert!(store.nonces.lock().unwrap().is_empty());
+}
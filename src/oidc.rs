@@ -0,0 +1,319 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+use std::path::PathBuf;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http::HeaderMap;
+use serde::{Deserialize, Deserializer};
+use serde_derive::Deserialize;
+use serde_json::Value;
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::algorithm::Algorithm;
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::error::KeyProviderError;
+use crate::http_client;
+use crate::jwk::JsonWebKey;
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::key_provider::{GOOGLE_CERT_URL, JwksKeyProvider, max_age, successful};
+use crate::unverified_token::GOOGLE_ISSUERS;
+
+/// The issuer of Microsoft Entra ID tokens for any tenant
+pub const ENTRA_ISSUER: &str = "https://login.microsoftonline.com/{tenantid}/v2.0";
+const ENTRA_CONFIGURATION_URL: &str =
+    "https://login.microsoftonline.com/common/v2.0/.well-known/openid-configuration";
+
+/// How long a discovery document is kept at least, since many issuers send it
+/// without a max-age
+const MIN_CONFIGURATION_AGE: Duration = Duration::from_secs(5 * 60);
+
+#[cfg(feature = "blocking")]
+pub type OidcClient = GenericClient<RefCell<OidcProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioOidcClient = GenericClient<Arc<Mutex<OidcProvider>>>;
+
+// https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata
+#[derive(Deserialize, Clone, Debug)]
+pub struct ProviderConfiguration {
+    pub issuer: String,
+    pub jwks_uri: String,
+    /// Signing algorithms this crate supports, out of those the issuer uses for ID tokens
+    #[serde(default, deserialize_with = "known_algorithms")]
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+}
+
+fn known_algorithms<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Algorithm>, D::Error> {
+    Ok(Vec::<Value>::deserialize(deserializer)?
+        .into_iter()
+        .filter_map(|alg| serde_json::from_value(alg).ok())
+        .collect())
+}
+
+/// Finds the keys of an OpenID Connect issuer through its discovery document.
+///
+/// The document and the key set it points to are both cached according to
+/// their Cache-Control headers.
+pub struct OidcProvider {
+    issuer: String,
+    configuration_url: String,
+    configuration: Option<ProviderConfiguration>,
+    /// None for a preconfigured provider, whose configuration is never fetched
+    expiration_time: Option<Instant>,
+    keys: Option<JwksKeyProvider>,
+    snapshot_path: Option<PathBuf>,
+}
+
+fn configuration_url(issuer: &str) -> String {
+    format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    )
+}
+
+impl Default for OidcProvider {
+    fn default() -> Self {
+        Self::google()
+    }
+}
+
+impl OidcProvider {
+    /// The provider of `issuer`, discovered at `<issuer>/.well-known/openid-configuration`.
+    pub fn new(issuer: &str) -> Self {
+        Self::with_configuration_url(issuer, &configuration_url(issuer))
+    }
+
+    /// Discover the keys of `issuer` from a document at another location.
+    pub fn with_configuration_url(issuer: &str, configuration_url: &str) -> Self {
+        Self {
+            issuer: issuer.to_owned(),
+            configuration_url: configuration_url.to_owned(),
+            configuration: None,
+            expiration_time: Some(Instant::now()),
+            keys: None,
+            snapshot_path: None,
+        }
+    }
+
+    /// A provider for an issuer whose configuration is known, without discovery.
+    pub fn with_configuration(configuration: ProviderConfiguration) -> Self {
+        Self {
+            issuer: configuration.issuer.clone(),
+            configuration_url: configuration_url(&configuration.issuer),
+            keys: Some(JwksKeyProvider::new(&configuration.jwks_uri)),
+            configuration: Some(configuration),
+            expiration_time: None,
+            snapshot_path: None,
+        }
+    }
+
+    /// Google's sign-in issuer, preconfigured with the keys and algorithms of its
+    /// discovery document
+    pub fn google() -> Self {
+        let configuration = ProviderConfiguration {
+            issuer: GOOGLE_ISSUERS[0].to_owned(),
+            jwks_uri: GOOGLE_CERT_URL.to_owned(),
+            id_token_signing_alg_values_supported: vec![Algorithm::RS256],
+        };
+        Self::with_configuration(configuration)
+    }
+
+    /// Microsoft Entra ID's multi-tenant issuer, [ENTRA_ISSUER]
+    pub fn entra() -> Self {
+        Self::with_configuration_url(ENTRA_ISSUER, ENTRA_CONFIGURATION_URL)
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// The last discovery document that was fetched, or the preconfigured one
+    pub fn configuration(&self) -> Option<&ProviderConfiguration> {
+        self.configuration.as_ref()
+    }
+
+    /// Keep the keys of the issuer in a snapshot file, as [JwksKeyProvider::snapshot_path].
+    pub fn snapshot_path(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.keys = self.keys.map(|keys| keys.snapshot_path(path.clone()));
+        self.snapshot_path = Some(path);
+        self
+    }
+
+    fn cached_keys(&mut self) -> Option<&mut JwksKeyProvider> {
+        match self
+            .expiration_time
+            .is_none_or(|time| time > Instant::now())
+        {
+            true => self.keys.as_mut(),
+            false => None,
+        }
+    }
+
+    fn process_response(
+        &mut self,
+        headers: &HeaderMap,
+        text: &str,
+    ) -> Result<&mut JwksKeyProvider, KeyProviderError> {
+        let configuration: ProviderConfiguration = serde_json::from_str(text)
+            .map_err(|e| KeyProviderError::Parse(self.configuration_url.clone(), e.to_string()))?;
+        // https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderConfigurationValidation
+        if configuration.issuer != self.issuer {
+            return Err(KeyProviderError::IssuerMismatch(
+                self.issuer.clone(),
+                configuration.issuer,
+            ));
+        }
+        let previous_jwks_uri = self.configuration.as_ref().map(|c| c.jwks_uri.as_str());
+        if self.keys.is_none() || previous_jwks_uri != Some(configuration.jwks_uri.as_str()) {
+            let keys = JwksKeyProvider::new(&configuration.jwks_uri);
+            self.keys = Some(match self.snapshot_path {
+                Some(ref path) => keys.snapshot_path(path.clone()),
+                None => keys,
+            });
+        }
+        self.configuration = Some(configuration);
+        self.expiration_time = Some(Instant::now() + max_age(headers).max(MIN_CONFIGURATION_AGE));
+        Ok(self.keys.as_mut().unwrap())
+    }
+
+    /// Keys for algorithms the issuer does not sign ID tokens with are ignored.
+    fn supported(&self, key: Option<JsonWebKey>) -> Option<JsonWebKey> {
+        let algorithms = &self
+            .configuration
+            .as_ref()?
+            .id_token_signing_alg_values_supported;
+        key.filter(|key| algorithms.is_empty() || algorithms.contains(&key.algorithm()))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl KeyProvider for OidcProvider {
+    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        let key = match self.cached_keys() {
+            Some(keys) => keys.get_key(key_id)?,
+            None => {
+                let url = &self.configuration_url;
+                let result = successful(url, http_client::get_blocking(url, &HeaderMap::new()))?;
+                self.process_response(result.headers(), result.body())?
+                    .get_key(key_id)?
+            }
+        };
+        Ok(self.supported(key))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncKeyProvider for OidcProvider {
+    async fn get_key_async(
+        &mut self,
+        key_id: &str,
+    ) -> Result<Option<JsonWebKey>, KeyProviderError> {
+        let key = match self.cached_keys() {
+            Some(keys) => keys.get_key_async(key_id).await?,
+            None => {
+                let url = &self.configuration_url;
+                let result = successful(url, http_client::get_async(url, &HeaderMap::new()).await)?;
+                self.process_response(result.headers(), result.body())?
+                    .get_key_async(key_id)
+                    .await?
+            }
+        };
+        Ok(self.supported(key))
+    }
+}
+
+fn oidc_builder<KP>(issuer: &str, client_id: &str, key_provider: KP) -> GenericClientBuilder<KP> {
+    let builder = GenericClientBuilder::with_key_provider(client_id, key_provider);
+    match GOOGLE_ISSUERS.contains(&issuer) {
+        true => builder,
+        false => builder.issuers(&[issuer]),
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl OidcClient {
+    /// A [GenericClientBuilder] for ID tokens of `issuer` with audience `client_id`.
+    pub fn issuer_builder(
+        issuer: &str,
+        client_id: &str,
+    ) -> GenericClientBuilder<RefCell<OidcProvider>> {
+        oidc_builder(issuer, client_id, RefCell::new(OidcProvider::new(issuer)))
+    }
+    pub fn for_issuer(issuer: &str, client_id: &str) -> Self {
+        Self::issuer_builder(issuer, client_id).build()
+    }
+    /// A [GenericClientBuilder] for Microsoft Entra ID tokens of any tenant, unless
+    /// restricted with [GenericClientBuilder::tenants].
+    pub fn entra_builder(client_id: &str) -> GenericClientBuilder<RefCell<OidcProvider>> {
+        oidc_builder(ENTRA_ISSUER, client_id, RefCell::new(OidcProvider::entra()))
+    }
+    pub fn for_entra(client_id: &str) -> Self {
+        Self::entra_builder(client_id).build()
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioOidcClient {
+    /// A [GenericClientBuilder] for ID tokens of `issuer` with audience `client_id`.
+    pub fn issuer_builder(
+        issuer: &str,
+        client_id: &str,
+    ) -> GenericClientBuilder<Arc<Mutex<OidcProvider>>> {
+        let key_provider = Arc::new(Mutex::new(OidcProvider::new(issuer)));
+        oidc_builder(issuer, client_id, key_provider)
+    }
+    pub fn for_issuer(issuer: &str, client_id: &str) -> Self {
+        Self::issuer_builder(issuer, client_id).build()
+    }
+    /// A [GenericClientBuilder] for Microsoft Entra ID tokens of any tenant, unless
+    /// restricted with [GenericClientBuilder::tenants].
+    pub fn entra_builder(client_id: &str) -> GenericClientBuilder<Arc<Mutex<OidcProvider>>> {
+        let key_provider = Arc::new(Mutex::new(OidcProvider::entra()));
+        oidc_builder(ENTRA_ISSUER, client_id, key_provider)
+    }
+    pub fn for_entra(client_id: &str) -> Self {
+        Self::entra_builder(client_id).build()
+    }
+}
+
+#[test]
+fn test_provider_configuration() {
+    let mut provider = OidcProvider::new("https://idp.example.com/realms/main/");
+    assert_eq!(
+        provider.configuration_url,
+        "https://idp.example.com/realms/main/.well-known/openid-configuration"
+    );
+    let document = r#"{
+        "issuer": "https://idp.example.com/realms/main/",
+        "jwks_uri": "https://idp.example.com/realms/main/certs",
+        "id_token_signing_alg_values_supported": ["PS256", "RS256", "ES256"]
+    }"#;
+    assert!(
+        provider
+            .process_response(&HeaderMap::new(), document)
+            .is_ok()
+    );
+    // a document without a max-age is not fetched again for every key
+    assert!(provider.cached_keys().is_some());
+    let configuration = provider.configuration().unwrap();
+    assert_eq!(
+        configuration.id_token_signing_alg_values_supported,
+        [Algorithm::RS256, Algorithm::ES256]
+    );
+
+    let mut provider = OidcProvider::new("https://idp.example.com");
+    assert!(
+        provider
+            .process_response(&HeaderMap::new(), document)
+            .is_err()
+    );
+}
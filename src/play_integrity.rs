@@ -0,0 +1,260 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use aes_kw::KekAes256;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, Nonce, UnboundKey};
+use serde_derive::Deserialize;
+
+use crate::algorithm::Algorithm;
+use crate::base64_decode;
+use crate::error::{Error, InvalidError::InvalidClaims, InvalidError::TokenFormat, NonceError};
+use crate::jwk::JsonWebKey;
+use crate::nonce::NonceStore;
+
+// https://developer.android.com/google/play/integrity/verdicts#payload-format
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityPayload {
+    pub request_details: RequestDetails,
+    pub app_integrity: AppIntegrity,
+    pub device_integrity: DeviceIntegrity,
+    pub account_details: AccountDetails,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestDetails {
+    pub request_package_name: String,
+    /// Set by classic requests
+    pub nonce: Option<String>,
+    /// Set by standard requests
+    pub request_hash: Option<String>,
+    /// When the app requested the token, in milliseconds since the unix epoch
+    pub timestamp_millis: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppIntegrity {
+    pub app_recognition_verdict: AppRecognitionVerdict,
+    /// Not set for [AppRecognitionVerdict::Unevaluated]
+    pub package_name: Option<String>,
+    #[serde(default)]
+    pub certificate_sha256_digest: Vec<String>,
+    pub version_code: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AppRecognitionVerdict {
+    PlayRecognized,
+    UnrecognizedVersion,
+    Unevaluated,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceIntegrity {
+    /// Empty if the device has no integrity
+    #[serde(default)]
+    pub device_recognition_verdict: Vec<DeviceRecognitionVerdict>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeviceRecognitionVerdict {
+    MeetsDeviceIntegrity,
+    MeetsBasicIntegrity,
+    MeetsStrongIntegrity,
+    MeetsVirtualIntegrity,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDetails {
+    pub app_licensing_verdict: AppLicensingVerdict,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AppLicensingVerdict {
+    Licensed,
+    Unlicensed,
+    Unevaluated,
+    #[serde(other)]
+    Unknown,
+}
+
+/// How long after it was requested a token is accepted, unless configured otherwise
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize)]
+struct JweHeader {
+    alg: String,
+    enc: String,
+}
+
+#[derive(Deserialize)]
+struct JwsHeader {
+    alg: Algorithm,
+}
+
+/// Decrypts and verifies Play Integrity tokens with the response encryption keys
+/// downloaded from the Play Console, instead of calling the `decodeIntegrityToken` API.
+// https://developer.android.com/google/play/integrity/classic#decrypt-verify
+pub struct PlayIntegrityVerifier {
+    package_name: String,
+    decryption_key: KekAes256,
+    verification_key: JsonWebKey,
+    nonce_store: Option<Arc<dyn NonceStore>>,
+    max_age: Duration,
+}
+
+impl PlayIntegrityVerifier {
+    /// `decryption_key` is the base64 AES key and `verification_key` the base64 DER encoded
+    /// EC public key, as shown in the Play Console.
+    pub fn new(
+        package_name: &str,
+        decryption_key: &str,
+        verification_key: &str,
+    ) -> Result<Self, Error> {
+        use base64::{Engine as _, engine::general_purpose::STANDARD};
+        let decryption_key = STANDARD
+            .decode(decryption_key.trim())
+            .ok()
+            .and_then(|key| KekAes256::try_from(key.as_slice()).ok())
+            .ok_or(Error::InvalidKey("decryption key".to_string()))?;
+        let verification_key = STANDARD
+            .decode(verification_key.trim())
+            .ok()
+            .and_then(|der| JsonWebKey::from_p256_der("play-integrity", &der))
+            .ok_or(Error::InvalidKey("verification key".to_string()))?;
+        Ok(Self {
+            package_name: package_name.to_owned(),
+            decryption_key,
+            verification_key,
+            nonce_store: None,
+            max_age: DEFAULT_MAX_AGE,
+        })
+    }
+
+    /// Require every token to carry a `nonce` issued by `store`, and consume it.
+    pub fn nonce_store(mut self, store: Arc<dyn NonceStore>) -> Self {
+        self.nonce_store = Some(store);
+        self
+    }
+
+    /// Reject tokens requested longer than `max_age` ago. Defaults to ten minutes.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn verify_token(&self, token: &str) -> Result<IntegrityPayload, Error> {
+        let payload = self.decode(token)?;
+        if let Some(ref store) = self.nonce_store {
+            let nonce = payload.request_details.nonce.as_ref();
+            store.consume(nonce.ok_or(NonceError::Missing)?)?;
+        }
+        Ok(payload)
+    }
+
+    /// Verify a token requested with `nonce`, for callers that keep track of nonces themselves.
+    pub fn verify_token_with_nonce(
+        &self,
+        token: &str,
+        nonce: &str,
+    ) -> Result<IntegrityPayload, Error> {
+        let payload = self.decode(token)?;
+        match payload.request_details.nonce {
+            Some(ref claim) if claim == nonce => Ok(payload),
+            Some(_) => Err(Error::Nonce(NonceError::Unknown)),
+            None => Err(Error::Nonce(NonceError::Missing)),
+        }
+    }
+
+    fn decode(&self, token: &str) -> Result<IntegrityPayload, Error> {
+        let jws = self.decrypt(token.trim())?;
+        let payload = self.verify_signature(&jws)?;
+        let payload: IntegrityPayload = serde_json::from_slice(&payload)?;
+        if payload.request_details.request_package_name != self.package_name {
+            return Err(Error::InvalidToken(InvalidClaims(
+                "requestDetails.requestPackageName".to_string(),
+            )));
+        }
+        if payload
+            .app_integrity
+            .package_name
+            .as_ref()
+            .is_some_and(|name| *name != self.package_name)
+        {
+            return Err(Error::InvalidToken(InvalidClaims(
+                "appIntegrity.packageName".to_string(),
+            )));
+        }
+        let requested_at = payload.request_details.timestamp_millis.parse::<u64>();
+        let current_millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        if !requested_at.is_ok_and(|requested_at| {
+            current_millis.saturating_sub(requested_at) <= self.max_age.as_millis() as u64
+        }) {
+            return Err(Error::InvalidToken(InvalidClaims(
+                "requestDetails.timestampMillis".to_string(),
+            )));
+        }
+        Ok(payload)
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc7516#section-5.2
+    fn decrypt(&self, token: &str) -> Result<Vec<u8>, Error> {
+        let [encoded_header, encrypted_key, iv, ciphertext, tag] = token
+            .split('.')
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| Error::InvalidToken(TokenFormat("JWE".to_string())))?;
+        let header: JweHeader = serde_json::from_slice(&base64_decode(encoded_header)?)?;
+        if header.alg != "A256KW" || header.enc != "A256GCM" {
+            return Err(Error::InvalidToken(TokenFormat(format!(
+                "JWE encryption {}/{}",
+                header.alg, header.enc
+            ))));
+        }
+        let mut content_key = [0; 32];
+        self.decryption_key
+            .unwrap(&base64_decode(encrypted_key)?, &mut content_key)
+            .map_err(|_| ring::error::Unspecified)?;
+        let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &content_key)?);
+        let nonce = Nonce::try_assume_unique_for_key(&base64_decode(iv)?)?;
+        let mut in_out = base64_decode(ciphertext)?;
+        in_out.extend(base64_decode(tag)?);
+        let plaintext_len = key
+            .open_in_place(nonce, Aad::from(encoded_header.as_bytes()), &mut in_out)?
+            .len();
+        in_out.truncate(plaintext_len);
+        Ok(in_out)
+    }
+
+    fn verify_signature(&self, jws: &[u8]) -> Result<Vec<u8>, Error> {
+        let jws = std::str::from_utf8(jws)
+            .map_err(|_| Error::InvalidToken(TokenFormat("JWS".to_string())))?;
+        let [encoded_header, encoded_payload, signature] = jws
+            .split('.')
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| Error::InvalidToken(TokenFormat("JWS".to_string())))?;
+        let header: JwsHeader = serde_json::from_slice(&base64_decode(encoded_header)?)?;
+        if header.alg != self.verification_key.algorithm() {
+            return Err(Error::UnsupportedAlgorithm(header.alg));
+        }
+        let signed_body = &jws[..encoded_header.len() + 1 + encoded_payload.len()];
+        self.verification_key
+            .verify(signed_body.as_bytes(), &base64_decode(signature)?)?;
+        Ok(base64_decode(encoded_payload)?)
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(feature = "config")]
+use std::path::PathBuf;
+#[cfg(feature = "config")]
+use std::sync::Weak;
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "config")]
+use std::time::{Duration, SystemTime};
+
+use crate::client::{GenericClientBuilder, Policy};
+#[cfg(feature = "config")]
+use crate::config::VerifierConfig;
+#[cfg(feature = "config")]
+use crate::error::{ConfigError, Error};
+
+/// Replaces the policy of a running client, obtained from `policy_handle()`.
+///
+/// Tokens being verified keep the policy they started with. The key provider, nonce store
+/// and replay cache of the client are not affected.
+#[derive(Clone)]
+pub struct PolicyHandle {
+    policy: Arc<RwLock<Arc<Policy>>>,
+}
+
+impl PolicyHandle {
+    pub(crate) fn new(policy: Arc<RwLock<Arc<Policy>>>) -> Self {
+        Self { policy }
+    }
+
+    /// Apply the client ID, issuers, email rules, client policies and leeway of `builder`.
+    /// Its key provider, nonce store and replay cache are ignored.
+    pub fn replace<T>(&self, builder: GenericClientBuilder<T>) {
+        *self.policy.write().unwrap() = Arc::new(builder.into_policy());
+    }
+
+    /// Apply a configuration, except for its replay cache capacity. Settings a
+    /// configuration cannot express, such as tenants, keep their current value, and
+    /// issuers or an email policy it leaves out are those of the client before any
+    /// configuration.
+    #[cfg(feature = "config")]
+    pub fn reload(&self, config: &VerifierConfig) {
+        let mut policy = self.policy.write().unwrap();
+        let builder = GenericClientBuilder::reconfigure(&policy, &config.client_id);
+        *policy = Arc::new(config.configure(builder).into_policy());
+    }
+
+    /// Reload the configuration from `path` whenever the file changes.
+    #[cfg(feature = "config")]
+    pub fn watch(&self, path: impl Into<PathBuf>) -> ConfigWatcher {
+        ConfigWatcher {
+            policy: Arc::downgrade(&self.policy),
+            path: path.into(),
+            version: None,
+        }
+    }
+}
+
+/// Reloads a configuration file into a client when its modification time or length changes.
+///
+/// A file that cannot be loaded leaves the previous policy in place.
+#[cfg(feature = "config")]
+pub struct ConfigWatcher {
+    // does not keep the client's policy alive once the client and its handles are gone
+    policy: Weak<RwLock<Arc<Policy>>>,
+    path: PathBuf,
+    /// The modification time and length of the file last loaded
+    version: Option<(SystemTime, u64)>,
+}
+
+#[cfg(feature = "config")]
+impl ConfigWatcher {
+    /// Reload the file if it changed since the last call; true if it was reloaded.
+    pub fn poll(&mut self) -> Result<bool, Error> {
+        let policy = self
+            .policy
+            .upgrade()
+            .ok_or(ConfigError::Invalid("the client was dropped".to_string()))?;
+        let version = std::fs::metadata(&self.path)
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+            .map_err(|e| ConfigError::Io(format!("{:?}: {e}", self.path)))?;
+        if self.version == Some(version) {
+            return Ok(false);
+        }
+        let config = VerifierConfig::from_file(&self.path)?;
+        PolicyHandle::new(policy).reload(&config);
+        self.version = Some(version);
+        Ok(true)
+    }
+
+    /// Poll the file every `interval` on a background thread, until the client and all
+    /// its policy handles are dropped.
+    pub fn spawn(mut self, interval: Duration) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            while self.policy.strong_count() > 0 {
+                if let Err(e) = self.poll() {
+                    log::warn!("not reloading {:?}: {e}", self.path);
+                }
+                std::thread::sleep(interval);
+            }
+        })
+    }
+}
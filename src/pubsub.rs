@@ -0,0 +1,171 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer};
+use serde_derive::Deserialize;
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::bearer_token;
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::email_policy::EmailPolicy;
+use crate::error::Error;
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+use crate::key_provider::GoogleKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::token::{IdPayload, Token};
+
+#[cfg(feature = "blocking")]
+pub type PubSubPushVerifier = GenericPubSubPushVerifier<RefCell<GoogleKeyProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioPubSubPushVerifier = GenericPubSubPushVerifier<Arc<Mutex<GoogleKeyProvider>>>;
+
+/// Verifies the OIDC token that Pub/Sub sends with push deliveries.
+///
+/// The token is a Google ID token whose `email` is the push subscription's service account.
+pub struct GenericPubSubPushVerifier<KP> {
+    client: GenericClient<KP>,
+}
+
+impl<KP> From<GenericClient<KP>> for GenericPubSubPushVerifier<KP> {
+    fn from(client: GenericClient<KP>) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl PubSubPushVerifier {
+    /// A [GenericClientBuilder] for tokens with `audience`, sent by one of `service_accounts`
+    /// (exact addresses or glob patterns). No caller is accepted if none are given.
+    pub fn builder(
+        audience: &str,
+        service_accounts: &[&str],
+    ) -> GenericClientBuilder<RefCell<GoogleKeyProvider>> {
+        GenericClientBuilder::<RefCell<GoogleKeyProvider>>::new(audience)
+            .service_accounts(service_accounts)
+    }
+    /// A [GenericClientBuilder] for tokens with `audience`, sent by any service account with a
+    /// verified email. Anyone who can mint a Google ID token for `audience` is accepted.
+    pub fn any_service_account_builder(
+        audience: &str,
+    ) -> GenericClientBuilder<RefCell<GoogleKeyProvider>> {
+        GenericClientBuilder::<RefCell<GoogleKeyProvider>>::new(audience)
+            .email_policy(EmailPolicy::new())
+    }
+    pub fn new(audience: &str, service_accounts: &[&str]) -> Self {
+        Self::from(Self::builder(audience, service_accounts).build())
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioPubSubPushVerifier {
+    /// A [GenericClientBuilder] for tokens with `audience`, sent by one of `service_accounts`
+    /// (exact addresses or glob patterns). No caller is accepted if none are given.
+    pub fn builder(
+        audience: &str,
+        service_accounts: &[&str],
+    ) -> GenericClientBuilder<Arc<Mutex<GoogleKeyProvider>>> {
+        GenericClientBuilder::<Arc<Mutex<GoogleKeyProvider>>>::new(audience)
+            .service_accounts(service_accounts)
+    }
+    /// A [GenericClientBuilder] for tokens with `audience`, sent by any service account with a
+    /// verified email. Anyone who can mint a Google ID token for `audience` is accepted.
+    pub fn any_service_account_builder(
+        audience: &str,
+    ) -> GenericClientBuilder<Arc<Mutex<GoogleKeyProvider>>> {
+        GenericClientBuilder::<Arc<Mutex<GoogleKeyProvider>>>::new(audience)
+            .email_policy(EmailPolicy::new())
+    }
+    pub fn new(audience: &str, service_accounts: &[&str]) -> Self {
+        Self::from(Self::builder(audience, service_accounts).build())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> GenericPubSubPushVerifier<RefCell<KP>> {
+    /// Verify the `Authorization` header of a push request, with or without the `Bearer` scheme.
+    pub fn verify(&self, authorization: &str) -> Result<Token<IdPayload>, Error> {
+        self.client.verify_id_token(bearer_token(authorization))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> GenericPubSubPushVerifier<Arc<Mutex<KP>>> {
+    /// Verify the `Authorization` header of a push request, with or without the `Bearer` scheme.
+    pub async fn verify_async(&self, authorization: &str) -> Result<Token<IdPayload>, Error> {
+        self.client
+            .verify_id_token_async(bearer_token(authorization))
+            .await
+    }
+}
+
+// https://cloud.google.com/pubsub/docs/push#receive_push
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PushEnvelope {
+    pub message: PushMessage,
+    pub subscription: String,
+    pub delivery_attempt: Option<u32>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PushMessage {
+    /// The message payload, decoded from base64
+    #[serde(default, deserialize_with = "base64_data")]
+    pub data: Vec<u8>,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+    pub message_id: String,
+    pub publish_time: String,
+    pub ordering_key: Option<String>,
+}
+
+fn base64_data<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    STANDARD
+        .decode(String::deserialize(deserializer)?)
+        .map_err(serde::de::Error::custom)
+}
+
+impl PushEnvelope {
+    /// Decode the JSON body of a push request.
+    pub fn from_slice(body: &[u8]) -> Result<Self, Error> {
+        let envelope: PushEnvelope =
+            serde_json::from_slice(body).map_err(|e| Error::InvalidPushMessage(e.to_string()))?;
+        if envelope.subscription.is_empty() || envelope.message.message_id.is_empty() {
+            return Err(Error::InvalidPushMessage(
+                "missing subscription or message id".to_string(),
+            ));
+        }
+        Ok(envelope)
+    }
+}
+
+#[test]
+fn test_push_envelope() {
+    let body = br#"{
+        "message": {
+            "attributes": {"key": "value"},
+            "data": "SGVsbG8gQ2xvdWQgUHViL1N1YiEgSGVyZSBpcyBteSBtZXNzYWdlIQ==",
+            "messageId": "2070443601311540",
+            "message_id": "2070443601311540",
+            "publishTime": "2021-02-26T19:13:55.749Z",
+            "publish_time": "2021-02-26T19:13:55.749Z"
+        },
+        "subscription": "projects/myproject/subscriptions/mysubscription"
+    }"#;
+    let envelope = PushEnvelope::from_slice(body).unwrap();
+    assert_eq!(
+        envelope.message.data,
+        b"Hello Cloud Pub/Sub! Here is my message!"
+    );
+    assert_eq!(envelope.message.attributes["key"], "value");
+    assert!(PushEnvelope::from_slice(br#"{"message": {"data": "%"}}"#).is_err());
+}
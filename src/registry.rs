@@ -0,0 +1,359 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::apple::{ApplePayload, GenericAppleClient};
+use crate::client::GenericClient;
+use crate::error::Error;
+use crate::firebase::{FirebasePayload, GenericFirebaseClient};
+use crate::iap::{GenericIapClient, IapPayload};
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::risc::{GenericRiscClient, SecurityEventPayload};
+use crate::token::{IdPayload, Token};
+use crate::unverified_token::{TENANT_PLACEHOLDER, peek_issuer};
+
+#[cfg(feature = "blocking")]
+pub type VerifierRegistry = GenericVerifierRegistry<dyn Verifier>;
+
+#[cfg(feature = "async")]
+pub type TokioVerifierRegistry = GenericVerifierRegistry<dyn AsyncVerifier>;
+
+/// A token verified by one of the verifiers of a [GenericVerifierRegistry]
+#[derive(Debug)]
+pub enum VerifiedToken {
+    Google(Token<IdPayload>),
+    /// An ID token of another OpenID Connect issuer
+    Oidc(Token<IdPayload>),
+    FirebaseIdToken(Token<FirebasePayload>),
+    FirebaseSessionCookie(Token<FirebasePayload>),
+    Iap(Token<IapPayload>),
+    Apple(Token<ApplePayload>),
+    SecurityEvent(Token<SecurityEventPayload>),
+}
+
+/// Verifies the tokens routed to one registered client, whatever its key provider
+#[cfg(feature = "blocking")]
+pub trait Verifier {
+    fn verify(&self, issuer: &str, token_string: &str) -> Result<VerifiedToken, Error>;
+}
+
+#[cfg(feature = "async")]
+type VerifyFuture<'a> = Pin<Box<dyn Future<Output = Result<VerifiedToken, Error>> + 'a>>;
+
+/// Verifies the tokens routed to one registered client, whatever its key provider
+#[cfg(feature = "async")]
+pub trait AsyncVerifier {
+    fn verify_async<'a>(&'a self, issuer: &'a str, token_string: &'a str) -> VerifyFuture<'a>;
+}
+
+struct GoogleVerifier<C>(C);
+struct OidcVerifier<C>(C);
+
+/// Verifies tokens of several issuers, choosing the verifier by the unverified `iss` claim.
+///
+/// Tokens of other issuers, or without a key ID, are rejected before any key is fetched.
+/// Issuers with a `{tenantid}` placeholder match the issuer of any tenant, and the client
+/// then checks the `tid` claim. Each client keeps its own key provider.
+///
+/// Registering another verifier for an issuer replaces the previous one. The issuers of
+/// a client are read when it is registered, and not updated by its `policy_handle()`.
+pub struct GenericVerifierRegistry<V: ?Sized> {
+    verifiers: Vec<Box<V>>,
+    // issuer -> index of its verifier
+    routes: HashMap<String, usize>,
+    // issuers with a tenant placeholder, checked in order when no issuer matches exactly
+    templates: Vec<(String, usize)>,
+}
+
+impl<V: ?Sized> Default for GenericVerifierRegistry<V> {
+    fn default() -> Self {
+        Self {
+            verifiers: vec![],
+            routes: HashMap::new(),
+            templates: vec![],
+        }
+    }
+}
+
+fn template_matches(template: &str, issuer: &str) -> bool {
+    let Some((prefix, suffix)) = template.split_once(TENANT_PLACEHOLDER) else {
+        return false;
+    };
+    issuer
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.strip_suffix(suffix))
+        .is_some_and(|tenant| !tenant.is_empty() && !tenant.contains('/'))
+}
+
+impl<V: ?Sized> GenericVerifierRegistry<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(mut self, issuers: Vec<String>, verifier: Box<V>) -> Self {
+        let index = self.verifiers.len();
+        for issuer in issuers {
+            if issuer.contains(TENANT_PLACEHOLDER) {
+                self.templates.retain(|(template, _)| *template != issuer);
+                self.templates.push((issuer, index));
+            } else {
+                self.routes.insert(issuer, index);
+            }
+        }
+        self.verifiers.push(verifier);
+        self
+    }
+
+    fn route(&self, token_string: &str) -> Result<(&V, String), Error> {
+        let issuer = peek_issuer(token_string)?;
+        let index = self.routes.get(&issuer).copied().or_else(|| {
+            let mut templates = self.templates.iter();
+            templates
+                .find(|(template, _)| template_matches(template, &issuer))
+                .map(|&(_, index)| index)
+        });
+        match index {
+            Some(index) => Ok((&*self.verifiers[index], issuer)),
+            None => Err(Error::UnknownIssuer(issuer)),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl VerifierRegistry {
+    /// Verify Google ID tokens with `client`, for its issuers.
+    pub fn register_google<KP: KeyProvider + 'static>(
+        self,
+        client: GenericClient<RefCell<KP>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(GoogleVerifier(client)))
+    }
+
+    /// Verify ID tokens of other OpenID Connect issuers, such as Microsoft Entra ID, with
+    /// `client`.
+    pub fn register_oidc<KP: KeyProvider + 'static>(
+        self,
+        client: GenericClient<RefCell<KP>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(OidcVerifier(client)))
+    }
+
+    /// Verify Firebase ID tokens and session cookies with `client`.
+    pub fn register_firebase<KP: KeyProvider + 'static>(
+        self,
+        client: GenericFirebaseClient<RefCell<KP>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(client))
+    }
+
+    /// Verify IAP assertions with `client`.
+    pub fn register_iap<KP: KeyProvider + 'static>(
+        self,
+        client: GenericIapClient<RefCell<KP>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(client))
+    }
+
+    /// Verify Sign in with Apple identity tokens with `client`.
+    pub fn register_apple<KP: KeyProvider + 'static>(
+        self,
+        client: GenericAppleClient<RefCell<KP>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(client))
+    }
+
+    /// Verify RISC security event tokens with `client`.
+    pub fn register_risc<KP: KeyProvider + 'static>(
+        self,
+        client: GenericRiscClient<RefCell<KP>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(client))
+    }
+
+    pub fn verify(&self, token_string: &str) -> Result<VerifiedToken, Error> {
+        let (verifier, issuer) = self.route(token_string)?;
+        verifier.verify(&issuer, token_string)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> Verifier for GoogleVerifier<GenericClient<RefCell<KP>>> {
+    fn verify(&self, _: &str, token_string: &str) -> Result<VerifiedToken, Error> {
+        Ok(VerifiedToken::Google(self.0.verify_id_token(token_string)?))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> Verifier for OidcVerifier<GenericClient<RefCell<KP>>> {
+    fn verify(&self, _: &str, token_string: &str) -> Result<VerifiedToken, Error> {
+        Ok(VerifiedToken::Oidc(self.0.verify_id_token(token_string)?))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> Verifier for GenericFirebaseClient<RefCell<KP>> {
+    fn verify(&self, issuer: &str, token_string: &str) -> Result<VerifiedToken, Error> {
+        Ok(match self.is_session_cookie_issuer(issuer) {
+            true => VerifiedToken::FirebaseSessionCookie(self.verify_session_cookie(token_string)?),
+            false => VerifiedToken::FirebaseIdToken(self.verify_id_token(token_string)?),
+        })
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> Verifier for GenericIapClient<RefCell<KP>> {
+    fn verify(&self, _: &str, token_string: &str) -> Result<VerifiedToken, Error> {
+        Ok(VerifiedToken::Iap(self.verify_assertion(token_string)?))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> Verifier for GenericAppleClient<RefCell<KP>> {
+    fn verify(&self, _: &str, token_string: &str) -> Result<VerifiedToken, Error> {
+        Ok(VerifiedToken::Apple(self.verify_id_token(token_string)?))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> Verifier for GenericRiscClient<RefCell<KP>> {
+    fn verify(&self, _: &str, token_string: &str) -> Result<VerifiedToken, Error> {
+        Ok(VerifiedToken::SecurityEvent(
+            self.verify_event_token(token_string)?,
+        ))
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioVerifierRegistry {
+    /// Verify Google ID tokens with `client`, for its issuers.
+    pub fn register_google<KP: AsyncKeyProvider + 'static>(
+        self,
+        client: GenericClient<Arc<Mutex<KP>>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(GoogleVerifier(client)))
+    }
+
+    /// Verify ID tokens of other OpenID Connect issuers, such as Microsoft Entra ID, with
+    /// `client`.
+    pub fn register_oidc<KP: AsyncKeyProvider + 'static>(
+        self,
+        client: GenericClient<Arc<Mutex<KP>>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(OidcVerifier(client)))
+    }
+
+    /// Verify Firebase ID tokens and session cookies with `client`.
+    pub fn register_firebase<KP: AsyncKeyProvider + 'static>(
+        self,
+        client: GenericFirebaseClient<Arc<Mutex<KP>>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(client))
+    }
+
+    /// Verify IAP assertions with `client`.
+    pub fn register_iap<KP: AsyncKeyProvider + 'static>(
+        self,
+        client: GenericIapClient<Arc<Mutex<KP>>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(client))
+    }
+
+    /// Verify Sign in with Apple identity tokens with `client`.
+    pub fn register_apple<KP: AsyncKeyProvider + 'static>(
+        self,
+        client: GenericAppleClient<Arc<Mutex<KP>>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(client))
+    }
+
+    /// Verify RISC security event tokens with `client`.
+    pub fn register_risc<KP: AsyncKeyProvider + 'static>(
+        self,
+        client: GenericRiscClient<Arc<Mutex<KP>>>,
+    ) -> Self {
+        self.register(client.issuers(), Box::new(client))
+    }
+
+    pub async fn verify_async(&self, token_string: &str) -> Result<VerifiedToken, Error> {
+        let (verifier, issuer) = self.route(token_string)?;
+        verifier.verify_async(&issuer, token_string).await
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> AsyncVerifier for GoogleVerifier<GenericClient<Arc<Mutex<KP>>>> {
+    fn verify_async<'a>(&'a self, _: &'a str, token_string: &'a str) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            let token = self.0.verify_id_token_async(token_string).await?;
+            Ok(VerifiedToken::Google(token))
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> AsyncVerifier for OidcVerifier<GenericClient<Arc<Mutex<KP>>>> {
+    fn verify_async<'a>(&'a self, _: &'a str, token_string: &'a str) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            let token = self.0.verify_id_token_async(token_string).await?;
+            Ok(VerifiedToken::Oidc(token))
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> AsyncVerifier for GenericFirebaseClient<Arc<Mutex<KP>>> {
+    fn verify_async<'a>(&'a self, issuer: &'a str, token_string: &'a str) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            Ok(match self.is_session_cookie_issuer(issuer) {
+                true => VerifiedToken::FirebaseSessionCookie(
+                    self.verify_session_cookie_async(token_string).await?,
+                ),
+                false => {
+                    VerifiedToken::FirebaseIdToken(self.verify_id_token_async(token_string).await?)
+                }
+            })
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> AsyncVerifier for GenericIapClient<Arc<Mutex<KP>>> {
+    fn verify_async<'a>(&'a self, _: &'a str, token_string: &'a str) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            let token = self.verify_assertion_async(token_string).await?;
+            Ok(VerifiedToken::Iap(token))
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> AsyncVerifier for GenericAppleClient<Arc<Mutex<KP>>> {
+    fn verify_async<'a>(&'a self, _: &'a str, token_string: &'a str) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            let token = self.verify_id_token_async(token_string).await?;
+            Ok(VerifiedToken::Apple(token))
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> AsyncVerifier for GenericRiscClient<Arc<Mutex<KP>>> {
+    fn verify_async<'a>(&'a self, _: &'a str, token_string: &'a str) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            let token = self.verify_event_token_async(token_string).await?;
+            Ok(VerifiedToken::SecurityEvent(token))
+        })
+    }
+}
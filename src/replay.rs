@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Remembers accepted tokens until they expire, so each one can only be presented once.
+///
+/// Implementations are shared between threads, so state changes go through `&self`.
+pub trait ReplayCache: Send + Sync {
+    /// Record `token_id` until the unix timestamp `expires_at`.
+    /// Returns `false` if the token was already recorded and has not expired.
+    fn insert(&self, token_id: &str, expires_at: u64) -> bool;
+}
+
+#[derive(Default)]
+struct LruEntries {
+    tick: u64,
+    // token id -> (expires_at, last use)
+    entries: HashMap<String, (u64, u64)>,
+    // last use -> token id
+    recency: BTreeMap<u64, String>,
+}
+
+/// A [ReplayCache] holding at most `capacity` tokens, evicting the least recently seen.
+///
+/// An evicted token can be replayed, so the capacity should cover the number of tokens
+/// accepted within their lifetime (one hour for Google ID tokens).
+pub struct LruReplayCache {
+    capacity: usize,
+    state: Mutex<LruEntries>,
+}
+
+impl Default for LruReplayCache {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+impl LruReplayCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(LruEntries::default()),
+        }
+    }
+}
+
+impl ReplayCache for LruReplayCache {
+    fn insert(&self, token_id: &str, expires_at: u64) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        let seen = match state.entries.get_mut(token_id) {
+            Some((expiration, last_use)) => {
+                let old_use = std::mem::replace(last_use, tick);
+                let seen = *expiration >= now;
+                *expiration = expires_at;
+                state.recency.remove(&old_use);
+                seen
+            }
+            None => {
+                if state.entries.len() >= self.capacity
+                    && let Some((_, evicted)) = state.recency.pop_first()
+                {
+                    state.entries.remove(&evicted);
+                }
+                state
+                    .entries
+                    .insert(token_id.to_string(), (expires_at, tick));
+                false
+            }
+        };
+        state.recency.insert(tick, token_id.to_string());
+        !seen
+    }
+}
+
+#[test]
+fn test_lru_replay_cache() {
+    let cache = LruReplayCache::new(2);
+    assert!(cache.insert("a", u64::MAX));
+    assert!(!cache.insert("a", u64::MAX));
+    assert!(cache.insert("b", u64::MAX));
+    assert!(!cache.insert("a", u64::MAX));
+    // "b" is now the least recently seen
+    assert!(cache.insert("c", u64::MAX));
+    assert!(!cache.insert("a", u64::MAX));
+    assert!(cache.insert("b", u64::MAX));
+    // expired entries are accepted again
+    assert!(cache.insert("d", 0));
+    assert!(cache.insert("d", 0));
+}
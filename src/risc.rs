@@ -0,0 +1,222 @@
+#[cfg(feature = "blocking")]
+use std::cell::RefCell;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer};
+use serde_derive::Deserialize;
+use serde_json::{Map, Value};
+#[cfg(feature = "async")]
+use tokio::sync::Mutex;
+
+use crate::client::{GenericClient, GenericClientBuilder};
+use crate::error::Error;
+#[cfg(feature = "async")]
+use crate::key_provider::AsyncKeyProvider;
+#[cfg(feature = "blocking")]
+use crate::key_provider::KeyProvider;
+use crate::oidc::OidcProvider;
+use crate::token::Token;
+
+const RISC_CONFIGURATION_URL: &str = "https://accounts.google.com/.well-known/risc-configuration";
+const RISC_ISSUER: &str = "https://accounts.google.com/";
+
+#[cfg(feature = "blocking")]
+pub type RiscClient = GenericRiscClient<RefCell<OidcProvider>>;
+
+#[cfg(feature = "async")]
+pub type TokioRiscClient = GenericRiscClient<Arc<Mutex<OidcProvider>>>;
+
+fn risc_provider() -> OidcProvider {
+    OidcProvider::with_configuration_url(RISC_ISSUER, RISC_CONFIGURATION_URL)
+}
+
+// https://developers.google.com/identity/protocols/risc#handling_events
+#[derive(Deserialize, Clone, Debug)]
+pub struct SecurityEventPayload {
+    #[serde(deserialize_with = "security_events")]
+    pub events: Vec<SecurityEvent>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SecurityEvent {
+    SessionsRevoked {
+        subject: EventSubject,
+    },
+    /// All OAuth tokens of the subject were revoked
+    TokensRevoked {
+        subject: EventSubject,
+    },
+    /// A single OAuth token was revoked
+    TokenRevoked {
+        subject: EventSubject,
+    },
+    AccountDisabled {
+        subject: EventSubject,
+        /// `hijacking` or `bulk-account`, if given
+        reason: Option<String>,
+    },
+    AccountEnabled {
+        subject: EventSubject,
+    },
+    CredentialChangeRequired {
+        subject: EventSubject,
+    },
+    /// A test event requested through the RISC API
+    Verification {
+        state: Option<String>,
+    },
+    /// An event type this crate does not know about
+    Other {
+        event_type: String,
+        data: Value,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct EventSubject {
+    /// `iss-sub`, `id_token_claims` or `oauth_token`
+    pub subject_type: String,
+    #[serde(rename = "iss")]
+    pub issuer: Option<String>,
+    /// The Google account ID
+    #[serde(rename = "sub")]
+    pub subject: Option<String>,
+    pub email: Option<String>,
+    /// `refresh_token`, for `oauth_token` subjects
+    pub token_type: Option<String>,
+    pub token_identifier_alg: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EventData {
+    subject: Option<EventSubject>,
+    reason: Option<String>,
+    state: Option<String>,
+}
+
+fn security_events<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<SecurityEvent>, D::Error> {
+    use serde::de::Error as _;
+    let events = Map::deserialize(deserializer)?;
+    let mut result = Vec::with_capacity(events.len());
+    for (event_type, data) in events {
+        let name = event_type
+            .strip_prefix("https://schemas.openid.net/secevent/")
+            .unwrap_or_default();
+        // only known event types have to follow the RISC data format
+        let fields = || EventData::deserialize(&data).map_err(D::Error::custom);
+        let required = |subject: Option<EventSubject>| {
+            subject.ok_or_else(|| D::Error::missing_field("subject"))
+        };
+        result.push(match name {
+            "risc/event-type/sessions-revoked" => SecurityEvent::SessionsRevoked {
+                subject: required(fields()?.subject)?,
+            },
+            "oauth/event-type/tokens-revoked" => SecurityEvent::TokensRevoked {
+                subject: required(fields()?.subject)?,
+            },
+            "oauth/event-type/token-revoked" => SecurityEvent::TokenRevoked {
+                subject: required(fields()?.subject)?,
+            },
+            "risc/event-type/account-disabled" => {
+                let EventData {
+                    subject, reason, ..
+                } = fields()?;
+                SecurityEvent::AccountDisabled {
+                    subject: required(subject)?,
+                    reason,
+                }
+            }
+            "risc/event-type/account-enabled" => SecurityEvent::AccountEnabled {
+                subject: required(fields()?.subject)?,
+            },
+            "risc/event-type/account-credential-change-required" => {
+                SecurityEvent::CredentialChangeRequired {
+                    subject: required(fields()?.subject)?,
+                }
+            }
+            "risc/event-type/verification" => SecurityEvent::Verification {
+                state: fields()?.state,
+            },
+            _ => SecurityEvent::Other { event_type, data },
+        });
+    }
+    Ok(result)
+}
+
+/// Receives the Security Event Tokens that Cross-Account Protection (RISC) sends
+/// to the event receiver of a project.
+///
+/// SETs have no `exp` claim, so tokens are accepted regardless of age.
+/// A [crate::ReplayCache] can reject tokens delivered more than once.
+pub struct GenericRiscClient<KP> {
+    client: GenericClient<KP>,
+}
+
+impl<KP> From<GenericClient<KP>> for GenericRiscClient<KP> {
+    fn from(client: GenericClient<KP>) -> Self {
+        Self { client }
+    }
+}
+
+impl<KP> GenericRiscClient<KP> {
+    pub(crate) fn issuers(&self) -> Vec<String> {
+        self.client.issuers()
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl RiscClient {
+    /// A [GenericClientBuilder] for SETs sent to the project of `client_id`.
+    pub fn builder(client_id: &str) -> GenericClientBuilder<RefCell<OidcProvider>> {
+        GenericClientBuilder::with_key_provider(client_id, RefCell::new(risc_provider()))
+            .issuers(&[RISC_ISSUER])
+            .allow_missing_expiration()
+            .allow_missing_subject()
+    }
+    pub fn new(client_id: &str) -> Self {
+        Self::from(Self::builder(client_id).build())
+    }
+}
+
+#[cfg(feature = "async")]
+impl TokioRiscClient {
+    /// A [GenericClientBuilder] for SETs sent to the project of `client_id`.
+    pub fn builder(client_id: &str) -> GenericClientBuilder<Arc<Mutex<OidcProvider>>> {
+        let key_provider = Arc::new(Mutex::new(risc_provider()));
+        GenericClientBuilder::with_key_provider(client_id, key_provider)
+            .issuers(&[RISC_ISSUER])
+            .allow_missing_expiration()
+            .allow_missing_subject()
+    }
+    pub fn new(client_id: &str) -> Self {
+        Self::from(Self::builder(client_id).build())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<KP: KeyProvider> GenericRiscClient<RefCell<KP>> {
+    /// Verify the body of a request to the event receiver.
+    pub fn verify_event_token(
+        &self,
+        token_string: &str,
+    ) -> Result<Token<SecurityEventPayload>, Error> {
+        self.client.verify_token_with_payload(token_string.trim())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<KP: AsyncKeyProvider> GenericRiscClient<Arc<Mutex<KP>>> {
+    /// Verify the body of a request to the event receiver.
+    pub async fn verify_event_token_async(
+        &self,
+        token_string: &str,
+    ) -> Result<Token<SecurityEventPayload>, Error> {
+        self.client
+            .verify_token_with_payload_async(token_string.trim())
+            .await
+    }
+}
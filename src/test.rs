@@ -1,5 +1,9 @@
 use std::sync::Arc;
 
+use base64::{
+    Engine as _, engine::general_purpose::STANDARD, engine::general_purpose::URL_SAFE_NO_PAD,
+};
+
 use super::*;
 #[cfg(feature = "async")]
 use crate::client::TokioClient;
@@ -31,6 +35,21 @@ const JWKS: &'static str = r#"{
       "kid": "adf5e710edfebecbefa9a61495654d03c0b8edf8",
       "e": "AQAB",
       "alg": "RS256"
+    },
+    {
+      "n": "uRPU6895XbSPu8o5YcAC2dx7DO3aRWeoXUfMjTIxo7bpHeiSGl52Lq4-aZ4Aj4govpMgXWpp68Ao4TB4OCYLCTZvmZnHmvCyuKymUrvbnii1_GGFwJpvu0pL3dRYkwiPnk-qlHZARdC_WAcnm1y5eAUTuvUvWoAMw7tWZdOuH7-bsztd6_7qrm8zja1Y5X2yU9qeddFBxH_K0c0dyz1-D8W-GztNY6FjNFZnsNrX7wrUnZDOaXda2xr02piIEGZIomoyfv-pBMtI-7CChddweA6l0FKXSKSNICY9_hQ9heOwOiMd8sbeNOj5Dvbv4pLYJR7KUQw2rB9yqmm3nMoHmw",
+      "kty": "RSA",
+      "kid": "test-key",
+      "e": "AQAB",
+      "alg": "RS256"
+    },
+    {
+      "kty": "EC",
+      "crv": "P-256",
+      "x": "QZedbhNif7gLZE5m2uM3aJ7dipfoj55M0eUBEgUoLxU",
+      "y": "ItNk8UwvrEGnCH7Osn9VfMKFjbHpOM-e-fgxrgjn7dE",
+      "kid": "test-ec-key",
+      "alg": "ES256"
     }
   ]
 }"#;
@@ -41,6 +60,141 @@ const KIDS: [&str; 2] = [
     "09bcf8028e06537d4d3ae4d84f5c5babcf2c0f0a",
     "a748e9f767159f667a0223318de0b2329e544362",
 ];
+// PKCS#8 private key matching the "test-key" entry of JWKS, used to sign fresh tokens
+const TEST_PRIVATE_KEY: &str = "MIIEvQIBADANBgkqhkiG9w0BAQEFAASCBKcwggSjAgEAAoIBAQC5E9Trz3ldtI+7yjlhwALZ3HsM7dpFZ6hdR8yNMjGjtukd6JIaXnYurj5pngCPiCi+kyBdamnrwCjhMHg4JgsJNm+Zmcea8LK4rKZSu9ueKLX8YYXAmm+7Skvd1FiTCI+eT6qUdkBF0L9YByebXLl4BRO69S9agAzDu1Zl064fv5uzO13r/uqubzONrVjlfbJT2p510UHEf8rRzR3LPX4Pxb4bO01joWM0Vmew2tfvCtSdkM5pd1rbGvTamIgQZkiiajJ+/6kEy0j7sIKF13B4DqXQUpdIpI0gJj3+FD2F47A6Ix3yxt406PkO9u/iktglHspRDDasH3KqabecygebAgMBAAECggEAEMECK1PyxqV2cCd+86GwIGIHJEUmgdXqYIIEEFwU258j5ziLJqaacuCvIbHTQdWvlTY4XdHTmNXNCDryXGgv45DxSqmob2w5TDfE2VnMKGXDDlUTCJEdFYqaPOXgxslQRvfV7JlFJDd2ECqa+UN26Vh8UBZV8mQDw8w3pqwiPRlcTD9qDzIxJt8HFficHw7IYt+B9kmPzc6dMM94aTwA+xynRz5rsN+PVT9GnOqO3ecssIM+T74QYTUi1ZtYDQgcY1XJXSvNJexIgysuuwTeni2nCIARhIMfFyT6c390zjEx4BIQHsu9iyKDJ1Kfm0YZdDR+A2vaeY1JbFipV/Mm4QKBgQD1QkicmJNRkaq5HWeLOhIuri1RE/O0spvk72g8GMljtFs7r/ee+xuBBVpCP27gKcUyrLvVsf1okcEEbO+jty1YCjqDmIdqoZsbQE81Ua9pBg7Lab47UWFI7EVXcslH7RghX5kbTpLvVMecWanBJ0yqtmhSKIaaysRbM6x3SmE5swKBgQDBLtMgbtMd09Wu1pUNojRGu6GHeOZZky5ct9GZFjqz9HlHa/fAvBhxo8+09Gf6gym7RPqmXvjVaCc4fLKD2MxeTtnXGrfkVDmYdBREZGqVXdnFQAqXi+7ovXDP2t35z+Js0y1mdKC3X9rIl7WnainAtTHmfAoAGjXTIOw92a42eQKBgAsgN6UK8tvnbMQ7BkB1oO+HLT0ZV5vh4lqPVb4sLa6dNupNQEFxCL1gnciVkhUPMAfteBPooCNHQWdO/cwCekcdJ6xilfoLXLruvukfTILKKI+nu157gZtKRJcD+QePROH2sT5g4R+ETA3Wr3G4s6ym27KmbfzFqASvImrC8AgrAoGBAMCtyVpx7xOy80/9KpF8lRNvlX6fcXEqQXWsydscttRCJKy93VBYyzCdKE/d7c+I/xiPhkhmNV31EhlfFlY26C+wCcpoFb4g24lZTdWhKkVsZAY/B9F7eoCcYmaOcBP36miMgJ3N+XasnnSwDDi8xIQ2tVyjnv3XJpQ8JCTUdtRRAoGADz6dfwidov0cI/QQ1GJXFZyilc+9p7+wUbC3viS4OqxA8MZhKN9TIgiX6hGbumHK2ZA6wz2LcNCEkxMSsLf7sFXvTlnPKTTIG9TZ+i5JHLMinjKYD9E4xeADZ/pL6T/DlyLpQeytadXRpWRd0yP4YMzyNjSv42i85vExmKYdeq4=";
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// The claims of a test token: `iss`, `aud`, a subject and a one hour lifetime, with
+/// `overrides` merged in. A `null` override removes the claim.
+fn claims(
+    issuer: &str,
+    audience: impl Into<serde_json::Value>,
+    overrides: serde_json::Value,
+) -> serde_json::Value {
+    let claims = serde_json::json!({
+        "iss": issuer,
+        "aud": audience.into(),
+        "sub": "105907900008170871586",
+        "iat": now(),
+        "exp": now() + 3600,
+    });
+    with(claims, overrides)
+}
+
+fn with(mut claims: serde_json::Value, overrides: serde_json::Value) -> serde_json::Value {
+    let claims_map = claims.as_object_mut().unwrap();
+    for (name, value) in overrides.as_object().unwrap() {
+        if value.is_null() {
+            claims_map.remove(name);
+        } else {
+            claims_map.insert(name.clone(), value.clone());
+        }
+    }
+    claims
+}
+
+fn id_claims() -> serde_json::Value {
+    claims(
+        "https://accounts.google.com",
+        AUDIENCE,
+        serde_json::json!({"email": EMAIL, "email_verified": true}),
+    )
+}
+
+fn firebase_claims() -> serde_json::Value {
+    claims(
+        "https://securetoken.google.com/test-project",
+        "test-project",
+        serde_json::json!({
+            "sub": "firebase-user",
+            "user_id": "firebase-user",
+            "auth_time": now() - 60,
+            "email": EMAIL,
+            "firebase": {
+                "sign_in_provider": "google.com",
+                "tenant": "tenant-1",
+                "identities": {"google.com": ["105907900008170871586"], "email": [EMAIL]}
+            }
+        }),
+    )
+}
+
+fn apple_claims() -> serde_json::Value {
+    claims(
+        "https://appleid.apple.com",
+        "com.example.app",
+        serde_json::json!({
+            "sub": "001234.abcdef0123456789.1234",
+            "email": "abc123@privaterelay.appleid.com",
+            "email_verified": "true",
+            "is_private_email": "true",
+            "real_user_status": 2,
+            "nonce": "n-0S6_WzA2Mj",
+            "nonce_supported": true,
+            "auth_time": now() - 5
+        }),
+    )
+}
+
+// PKCS#8 private key matching the "test-ec-key" entry of JWKS
+const TEST_EC_PRIVATE_KEY: &str = "MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQguG8AjSP5goCYpOJBiI0K/d+ZuAZL0pPcj5C3A4t4mx2hRANCAARBl51uE2J/uAtkTmba4zdont2Kl+iPnkzR5QESBSgvFSLTZPFML6xBpwh+zrJ/VXzChY2x6TjPnvn4Ma4I5+3R";
+
+fn sign_token_es256(claims: &serde_json::Value) -> String {
+    sign_token_es256_with(&STANDARD.decode(TEST_EC_PRIVATE_KEY).unwrap(), claims)
+}
+
+fn sign_token_es256_with(pkcs8: &[u8], claims: &serde_json::Value) -> String {
+    let rng = ring::rand::SystemRandom::new();
+    let key_pair = ring::signature::EcdsaKeyPair::from_pkcs8(
+        &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+        pkcs8,
+        &rng,
+    )
+    .unwrap();
+    sign_with("ES256", "test-ec-key", claims, |signed_body| {
+        key_pair.sign(&rng, signed_body).unwrap().as_ref().to_vec()
+    })
+}
+
+fn sign_token(claims: &serde_json::Value) -> String {
+    let key_pair =
+        ring::signature::RsaKeyPair::from_pkcs8(&STANDARD.decode(TEST_PRIVATE_KEY).unwrap())
+            .unwrap();
+    sign_with("RS256", "test-key", claims, |signed_body| {
+        let mut signature = vec![0; key_pair.public().modulus_len()];
+        key_pair
+            .sign(
+                &ring::signature::RSA_PKCS1_SHA256,
+                &ring::rand::SystemRandom::new(),
+                signed_body,
+                &mut signature,
+            )
+            .unwrap();
+        signature
+    })
+}
+
+fn sign_with(
+    alg: &str,
+    kid: &str,
+    claims: &serde_json::Value,
+    sign: impl FnOnce(&[u8]) -> Vec<u8>,
+) -> String {
+    let header = serde_json::json!({"alg": alg, "kid": kid, "typ": "JWT"});
+    let signed_body = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let signature = sign(signed_body.as_bytes());
+    format!("{}.{}", signed_body, URL_SAFE_NO_PAD.encode(signature))
+}
 
 #[derive(Default)]
 struct TestKeyProvider {
@@ -49,7 +203,7 @@ struct TestKeyProvider {
 
 #[cfg(feature = "blocking")]
 impl KeyProvider for TestKeyProvider {
-    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, ()> {
+    fn get_key(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, KeyProviderError> {
         let set: JsonWebKeySet = serde_json::from_str(JWKS).unwrap();
         *self.call_count.write().unwrap() += 1;
         Ok(set.get_key(key_id))
@@ -58,7 +212,10 @@ impl KeyProvider for TestKeyProvider {
 
 #[cfg(feature = "async")]
 impl AsyncKeyProvider for TestKeyProvider {
-    async fn get_key_async(&mut self, key_id: &str) -> Result<Option<JsonWebKey>, ()> {
+    async fn get_key_async(
+        &mut self,
+        key_id: &str,
+    ) -> Result<Option<JsonWebKey>, KeyProviderError> {
         let set: JsonWebKeySet = serde_json::from_str(JWKS).unwrap();
         *self.call_count.write().unwrap() += 1;
         Ok(set.get_key(key_id))
@@ -112,74 +269,1787 @@ pub fn test_id_token() {
     assert_eq!(id_token.payload.email, Some(EMAIL.to_string()));
 }
 
-#[cfg(feature = "async")]
-#[tokio::test]
-async fn decode_keys_async() {
-    for kid in KIDS.iter() {
-        TestKeyProvider::default().get_key_async(kid).await.unwrap();
-    }
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_signed_token() {
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    let id_token = client
+        .verify_id_token(&sign_token(&id_claims()))
+        .expect("id token should be valid");
+    assert_eq!(id_token.payload.email, Some(EMAIL.to_string()));
 }
 
-#[cfg(feature = "async")]
-#[tokio::test]
-async fn test_client_async() {
-    let client = TokioClient::builder(AUDIENCE)
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_multiple_audiences() {
+    let client = Client::builder(AUDIENCE)
         .custom_key_provider(TestKeyProvider::default())
         .build();
+    let mut claims = id_claims();
+    claims["aud"] = serde_json::json!([AUDIENCE]);
+    let id_token = client
+        .verify_id_token(&sign_token(&claims))
+        .expect("single audience array should be valid");
+    assert_eq!(id_token.claims.audience, AUDIENCE);
+
+    // other audiences are only trusted if the token was issued to this client
+    claims["aud"] = serde_json::json!(["other-client", AUDIENCE]);
     assert_eq!(
-        client.verify_token_async(TOKEN).await.map(|_| ()),
-        Err(Error::Expired)
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "azp".to_string()
+        )))
+    );
+    claims["azp"] = "other-client".into();
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "azp".to_string()
+        )))
     );
+    claims["azp"] = AUDIENCE.into();
+    let id_token = client
+        .verify_id_token(&sign_token(&claims))
+        .expect("token issued to this client should be valid");
+    assert_eq!(id_token.claims.audience, AUDIENCE);
 }
 
-#[cfg(feature = "async")]
-#[tokio::test]
-async fn test_client_invalid_client_id_async() {
-    let client = TokioClient::builder("invalid client id")
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_email_policy() {
+    let verify = |policy: EmailPolicy, claims: serde_json::Value| {
+        Client::builder(AUDIENCE)
+            .custom_key_provider(TestKeyProvider::default())
+            .email_policy(policy)
+            .build()
+            .verify_id_token(&sign_token(&claims))
+            .map(|_| ())
+    };
+    let mut unverified = id_claims();
+    unverified["email_verified"] = false.into();
+    assert_eq!(
+        verify(EmailPolicy::new(), unverified.clone()),
+        Err(Error::EmailNotVerified)
+    );
+    assert_eq!(
+        verify(EmailPolicy::new().allow_unverified(), unverified),
+        Ok(())
+    );
+    assert_eq!(
+        verify(EmailPolicy::new().allow_domain("example.com"), id_claims()),
+        Err(Error::EmailNotAllowed)
+    );
+    assert_eq!(
+        verify(EmailPolicy::new().allow_domain("gmail.com"), id_claims()),
+        Ok(())
+    );
+    assert_eq!(
+        verify(
+            EmailPolicy::new().deny_email("danjames.baumann+alias@gmail.com"),
+            id_claims()
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        verify(
+            EmailPolicy::new()
+                .normalize_gmail()
+                .deny_email("danjames.baumann+alias@gmail.com"),
+            id_claims()
+        ),
+        Err(Error::EmailNotAllowed)
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_id_token_claims() {
+    let client = Client::builder(AUDIENCE)
         .custom_key_provider(TestKeyProvider::default())
+        .unsafe_ignore_expiration()
         .build();
-    let result = client.verify_token_async(TOKEN).await.map(|_| ());
+    let id_token = client
+        .verify_id_token(TOKEN)
+        .expect("id token should be valid");
+    assert_eq!(id_token.claims.not_before, Some(1710947095));
     assert_eq!(
-        result,
+        id_token.claims.jwt_id.as_deref(),
+        Some("de685199e21d19b3ecc211e06c4d874b5ae08b1e")
+    );
+    assert_eq!(id_token.payload.azp.as_deref(), Some(AUDIENCE));
+    assert!(id_token.payload.extra.is_empty());
+    let raw: serde_json::Value = serde_json::from_str(&id_token.raw_payload).unwrap();
+    assert_eq!(raw["name"], "Dan Baumann");
+
+    let mut claims = id_claims();
+    claims["custom"] = "value".into();
+    let id_token = client.verify_id_token(&sign_token(&claims)).unwrap();
+    assert_eq!(id_token.payload.extra.len(), 1);
+    assert_eq!(id_token.payload.extra["custom"], "value");
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_access_token_hash() {
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    let mut claims = id_claims();
+    claims["at_hash"] = "77QmUPtjPfzWtF2AnpK9RQ".into();
+    let token = sign_token(&claims);
+    let access_token = "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y";
+    assert!(
+        client
+            .verify_id_token_with_access_token(&token, access_token)
+            .is_ok()
+    );
+    assert_eq!(
+        client
+            .verify_id_token_with_access_token(&token, "another access token")
+            .map(|_| ()),
+        Err(Error::HashMismatch("at_hash".to_string()))
+    );
+    assert_eq!(
+        client
+            .verify_id_token_with_code(&token, access_token)
+            .map(|_| ()),
         Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
-            "aud".to_string()
+            "c_hash".to_string()
         )))
     );
 }
 
-#[cfg(feature = "async")]
-#[tokio::test]
-async fn test_id_token_async() {
-    let client = TokioClient::builder(AUDIENCE)
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_nonce() {
+    let store = Arc::new(MemoryNonceStore::new(std::time::Duration::from_secs(60)));
+    let client = Client::builder(AUDIENCE)
         .custom_key_provider(TestKeyProvider::default())
-        .unsafe_ignore_expiration()
+        .nonce_store(store.clone())
         .build();
-    let id_token = client
-        .verify_id_token_async(TOKEN)
-        .await
-        .expect("id token should be valid");
-    assert_eq!(id_token.claims.audience, AUDIENCE);
-    assert_eq!(id_token.payload.domain, None);
-    assert_eq!(id_token.payload.email, Some(EMAIL.to_string()));
+    assert_eq!(
+        client
+            .verify_id_token(&sign_token(&id_claims()))
+            .map(|_| ()),
+        Err(Error::Nonce(NonceError::Missing))
+    );
+    let mut claims = id_claims();
+    claims["nonce"] = store.issue().unwrap().into();
+    let token = sign_token(&claims);
+    assert!(client.verify_id_token(&token).is_ok());
+    assert_eq!(
+        client.verify_id_token(&token).map(|_| ()),
+        Err(Error::Nonce(NonceError::Used))
+    );
 }
 
-#[cfg(feature = "async")]
-#[tokio::test]
-async fn test_deadlock_prevention() {
-    let client = TokioClient::builder(AUDIENCE)
-        .unsafe_ignore_expiration()
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_replay_cache() {
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .replay_cache(Arc::new(LruReplayCache::default()))
         .build();
-    join_all((0..10u8).map(|_| verify_token_async(&client))).await;
+    let token = sign_token(&id_claims());
+    assert!(client.verify_id_token(&token).is_ok());
+    assert_eq!(
+        client.verify_id_token(&token).map(|_| ()),
+        Err(Error::Replayed)
+    );
+    let mut claims = id_claims();
+    claims["jti"] = "token-1".into();
+    assert!(client.verify_id_token(&sign_token(&claims)).is_ok());
+    claims["iat"] = (now() - 1).into();
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::Replayed)
+    );
+    // a jti is only unique for its issuer
+    claims["iss"] = "accounts.google.com".into();
+    assert!(client.verify_id_token(&sign_token(&claims)).is_ok());
 }
 
-#[cfg(feature = "async")]
-async fn verify_token_async(client: &TokioClient) {
-    let result = client.verify_token_async(TOKEN).await;
-    assert!(matches!(
-        result,
-        Err(Error::InvalidToken(error::InvalidError::Json(_)))
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_replay_cache_with_leeway() {
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .leeway(std::time::Duration::from_secs(300))
+        .replay_cache(Arc::new(LruReplayCache::default()))
+        .build();
+    // expired, but still accepted within the leeway
+    let mut claims = id_claims();
+    claims["iat"] = (now() - 3700).into();
+    claims["exp"] = (now() - 100).into();
+    claims["jti"] = "token-1".into();
+    let token = sign_token(&claims);
+    assert!(client.verify_id_token(&token).is_ok());
+    assert_eq!(
+        client.verify_id_token(&token).map(|_| ()),
+        Err(Error::Replayed)
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_rejected_token_is_not_consumed() {
+    let store = Arc::new(MemoryNonceStore::new(std::time::Duration::from_secs(60)));
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .email_policy(EmailPolicy::new().allow_domain("example.com"))
+        .nonce_store(store.clone())
+        .replay_cache(Arc::new(LruReplayCache::default()))
+        .build();
+    let mut claims = id_claims();
+    claims["nonce"] = store.issue().unwrap().into();
+    claims["at_hash"] = "77QmUPtjPfzWtF2AnpK9RQ".into();
+    let token = sign_token(&claims);
+    let access_token = "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y";
+    assert_eq!(
+        client.verify_id_token(&token).map(|_| ()),
+        Err(Error::EmailNotAllowed)
+    );
+
+    client.policy_handle().replace(Client::builder(AUDIENCE));
+    assert_eq!(
+        client
+            .verify_id_token_with_access_token(&token, "another access token")
+            .map(|_| ()),
+        Err(Error::HashMismatch("at_hash".to_string()))
+    );
+    assert!(
+        client
+            .verify_id_token_with_access_token(&token, access_token)
+            .is_ok()
+    );
+    assert_eq!(
+        client.verify_id_token(&token).map(|_| ()),
+        Err(Error::Nonce(NonceError::Used))
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_firebase_id_token() {
+    let client = FirebaseClient::builder("test-project")
+        .custom_key_providers(TestKeyProvider::default(), TestKeyProvider::default())
+        .build();
+    let token = client
+        .verify_id_token(&sign_token(&firebase_claims()))
+        .expect("firebase token should be valid");
+    assert_eq!(token.claims.subject.as_deref(), Some("firebase-user"));
+    assert_eq!(token.payload.firebase.sign_in_provider, "google.com");
+    assert_eq!(token.payload.firebase.tenant.as_deref(), Some("tenant-1"));
+
+    let verify =
+        |claims: serde_json::Value| client.verify_id_token(&sign_token(&claims)).map(|_| ());
+    let invalid = |claim: &str| {
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            claim.to_string(),
+        )))
+    };
+    let mut claims = firebase_claims();
+    claims["auth_time"] = (now() + 600).into();
+    assert_eq!(verify(claims), invalid("auth_time"));
+    let mut claims = firebase_claims();
+    claims["sub"] = "".into();
+    assert_eq!(verify(claims), invalid("sub"));
+    let mut claims = firebase_claims();
+    claims["iss"] = "https://securetoken.google.com/other-project".into();
+    assert_eq!(verify(claims), invalid("iss"));
+    assert_eq!(verify(id_claims()), invalid("aud"));
+    assert_eq!(
+        client
+            .verify_session_cookie(&sign_token(&firebase_claims()))
+            .map(|_| ()),
+        invalid("iss")
+    );
+    let mut claims = firebase_claims();
+    claims["iss"] = "https://session.firebase.google.com/test-project".into();
+    claims["exp"] = (now() + 14 * 24 * 3600).into();
+    let cookie = client
+        .verify_session_cookie(&sign_token(&claims))
+        .expect("session cookie should be valid");
+    assert_eq!(cookie.payload.firebase.sign_in_provider, "google.com");
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_iap_assertion() {
+    let audience = backend_service_audience("1234", "5678");
+    assert_eq!(audience, "/projects/1234/global/backendServices/5678");
+    let client = GenericIapClient::from(
+        IapClient::builder(&audience)
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let claims = claims(
+        "https://cloud.google.com/iap",
+        audience,
+        serde_json::json!({
+            "sub": "accounts.google.com:105907900008170871586",
+            "email": EMAIL,
+            "google": {"access_levels": ["accessPolicies/1/accessLevels/corp"]}
+        }),
+    );
+    let token = client
+        .verify_assertion(&sign_token_es256(&claims))
+        .expect("IAP assertion should be valid");
+    assert_eq!(token.payload.email, EMAIL);
+    assert_eq!(
+        token.payload.google.unwrap().access_levels,
+        vec!["accessPolicies/1/accessLevels/corp"]
+    );
+
+    let assertion = sign_token_es256(&claims);
+    let (signed_body, signature) = assertion.rsplit_once('.').unwrap();
+    let mut signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+    signature[0] ^= 1;
+    let forged = format!("{signed_body}.{}", URL_SAFE_NO_PAD.encode(signature));
+    assert_eq!(
+        client.verify_assertion(&forged).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::Crypto))
+    );
+}
+
+/// The same ES256 signature with `s` replaced by `n - s`, which verifies just as well
+#[cfg(feature = "blocking")]
+fn malleate_es256(token: &str) -> String {
+    // the order of the P-256 group
+    const N: [u8; 32] = [
+        0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xbc, 0xe6, 0xfa, 0xad, 0xa7, 0x17, 0x9e, 0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63,
+        0x25, 0x51,
+    ];
+    let (signed_body, signature) = token.rsplit_once('.').unwrap();
+    let mut signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+    let mut borrow = 0;
+    for i in (0..32).rev() {
+        let difference = N[i] as i16 - signature[32 + i] as i16 - borrow;
+        borrow = (difference < 0) as i16;
+        signature[32 + i] = difference.rem_euclid(256) as u8;
+    }
+    format!("{signed_body}.{}", URL_SAFE_NO_PAD.encode(signature))
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_replay_cache_malleated_signature() {
+    let audience = backend_service_audience("1234", "5678");
+    let client = GenericIapClient::from(
+        IapClient::builder(&audience)
+            .custom_key_provider(TestKeyProvider::default())
+            .replay_cache(Arc::new(LruReplayCache::default()))
+            .build(),
+    );
+    let claims = claims(
+        "https://cloud.google.com/iap",
+        audience,
+        serde_json::json!({"sub": "accounts.google.com:105907900008170871586", "email": EMAIL}),
+    );
+    let assertion = sign_token_es256(&claims);
+    let malleated = malleate_es256(&assertion);
+    assert_ne!(malleated, assertion);
+    assert!(client.verify_assertion(&malleated).is_ok());
+    assert_eq!(
+        client.verify_assertion(&assertion).map(|_| ()),
+        Err(Error::Replayed)
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_pubsub_push() {
+    const PUSH_ACCOUNT: &str = "push@test-project.iam.gserviceaccount.com";
+    let verifier = GenericPubSubPushVerifier::from(
+        PubSubPushVerifier::builder("https://example.com/push", &[PUSH_ACCOUNT])
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let mut claims = id_claims();
+    claims["aud"] = "https://example.com/push".into();
+    claims["email"] = PUSH_ACCOUNT.into();
+    let token = verifier
+        .verify(&format!("Bearer {}", sign_token(&claims)))
+        .expect("push token should be valid");
+    assert_eq!(token.payload.email.as_deref(), Some(PUSH_ACCOUNT));
+
+    claims["email"] = "other@test-project.iam.gserviceaccount.com".into();
+    assert_eq!(
+        verifier.verify(&sign_token(&claims)).map(|_| ()),
+        Err(Error::EmailNotAllowed)
+    );
+    claims["email"] = PUSH_ACCOUNT.into();
+    claims["email_verified"] = false.into();
+    assert_eq!(
+        verifier.verify(&sign_token(&claims)).map(|_| ()),
+        Err(Error::EmailNotVerified)
+    );
+
+    claims["email_verified"] = true.into();
+    let verifier = |builder: crate::client::GenericClientBuilder<std::cell::RefCell<_>>| {
+        GenericPubSubPushVerifier::from(
+            builder
+                .custom_key_provider(TestKeyProvider::default())
+                .build(),
+        )
+    };
+    assert_eq!(
+        verifier(PubSubPushVerifier::builder("https://example.com/push", &[]))
+            .verify(&sign_token(&claims))
+            .map(|_| ()),
+        Err(Error::EmailNotAllowed)
+    );
+    let any = verifier(PubSubPushVerifier::any_service_account_builder(
+        "https://example.com/push",
+    ));
+    assert!(any.verify(&sign_token(&claims)).is_ok());
+    claims["email_verified"] = false.into();
+    assert_eq!(
+        any.verify(&sign_token(&claims)).map(|_| ()),
+        Err(Error::EmailNotVerified)
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_chat_request() {
+    const CHAT_ACCOUNT: &str = "chat@system.gserviceaccount.com";
+    let project_client = GenericChatClient::from(
+        ChatClient::project_number_builder("1234567890")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    // service account tokens have no subject
+    let claims = claims(CHAT_ACCOUNT, "1234567890", serde_json::json!({"sub": null}));
+    let token = project_client
+        .verify(&format!("Bearer {}", sign_token(&claims)))
+        .expect("chat token should be valid");
+    assert_eq!(token.claims.subject, None);
+
+    let url_client = GenericChatClient::from(
+        ChatClient::app_url_builder("https://example.com/chat")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let mut claims = id_claims();
+    claims["aud"] = "https://example.com/chat".into();
+    claims["email"] = CHAT_ACCOUNT.into();
+    assert!(url_client.verify(&sign_token(&claims)).is_ok());
+    // a Google ID token is not a Chat token for the project number
+    claims["aud"] = "1234567890".into();
+    assert_eq!(
+        project_client.verify(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "iss".to_string()
+        )))
+    );
+    // only tokens signed by Chat itself may lack a subject
+    claims["aud"] = "https://example.com/chat".into();
+    claims["sub"] = serde_json::Value::Null;
+    assert_eq!(
+        url_client.verify(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::MissingClaim(
+            "sub".to_string()
+        )))
+    );
+
+    claims["sub"] = "105907900008170871586".into();
+    claims["email"] = EMAIL.into();
+    assert_eq!(
+        url_client.verify(&sign_token(&claims)).map(|_| ()),
+        Err(Error::EmailNotAllowed)
+    );
+}
+
+fn app_check_claims() -> serde_json::Value {
+    claims(
+        "https://firebaseappcheck.googleapis.com/123456789",
+        serde_json::json!(["projects/123456789", "projects/test-project"]),
+        serde_json::json!({"sub": "1:123456789:web:abcdef"}),
+    )
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_app_check_token() {
+    let client = GenericAppCheckClient::from(
+        AppCheckClient::builder("123456789")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let mut claims = app_check_claims();
+    let token = client
+        .verify_token(&sign_token(&claims))
+        .expect("app check token should be valid");
+    assert_eq!(token.payload.app_id, "1:123456789:web:abcdef");
+    assert_eq!(token.claims.audience, "projects/123456789");
+
+    claims["aud"] = serde_json::json!(["projects/test-project"]);
+    assert_eq!(
+        client.verify_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "aud".to_string()
+        )))
+    );
+    let project_id_client = GenericAppCheckClient::from(
+        AppCheckClient::project_id_builder("123456789", "test-project")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let token = project_id_client
+        .verify_token(&sign_token(&claims))
+        .expect("app check token for the project ID should be valid");
+    assert_eq!(token.claims.audience, "projects/test-project");
+    assert_eq!(token.client_id, "projects/test-project");
+
+    claims["aud"] = serde_json::json!(["projects/987654321", "projects/other-project"]);
+    assert_eq!(
+        client.verify_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "aud".to_string()
+        )))
+    );
+    claims = app_check_claims();
+    claims["iss"] = "https://firebaseappcheck.googleapis.com/987654321".into();
+    assert_eq!(
+        client.verify_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "iss".to_string()
+        )))
+    );
+}
+
+fn security_event_claims() -> serde_json::Value {
+    claims(
+        "https://accounts.google.com/",
+        AUDIENCE,
+        serde_json::json!({
+            "sub": null,
+            "exp": null,
+            "iat": now() - 7 * 24 * 3600,
+            "jti": "756E69717565206964656E746966696572",
+            "events": {
+                "https://schemas.openid.net/secevent/risc/event-type/account-disabled": {
+                    "subject": {
+                        "subject_type": "iss-sub",
+                        "iss": "https://accounts.google.com/",
+                        "sub": "105907900008170871586"
+                    },
+                    "reason": "hijacking"
+                },
+                "https://schemas.openid.net/secevent/risc/event-type/verification": {
+                    "state": "test-state"
+                }
+            }
+        }),
+    )
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_security_event_token() {
+    let client = GenericRiscClient::from(
+        RiscClient::builder(AUDIENCE)
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let mut claims = security_event_claims();
+    let replayed = sign_token(&claims);
+    let token = client
+        .verify_event_token(&sign_token(&claims))
+        .expect("SET without exp should be valid");
+    assert_eq!(token.claims.expires_at, None);
+    assert_eq!(
+        token.payload.events[0],
+        SecurityEvent::AccountDisabled {
+            subject: EventSubject {
+                subject_type: "iss-sub".to_string(),
+                issuer: Some("https://accounts.google.com/".to_string()),
+                subject: Some("105907900008170871586".to_string()),
+                email: None,
+                token_type: None,
+                token_identifier_alg: None,
+                token: None,
+            },
+            reason: Some("hijacking".to_string()),
+        }
+    );
+    assert_eq!(
+        token.payload.events[1],
+        SecurityEvent::Verification {
+            state: Some("test-state".to_string())
+        }
+    );
+    // an unknown event type may use another subject format
+    let mut caep_claims = security_event_claims();
+    caep_claims["events"]["https://schemas.openid.net/secevent/caep/event-type/session-revoked"] =
+        serde_json::json!({"subject": {"format": "opaque", "id": "session-1"}});
+    let token = client
+        .verify_event_token(&sign_token(&caep_claims))
+        .expect("SET with an unknown event type should be valid");
+    assert!(token.payload.events.iter().any(|event| matches!(
+        event,
+        SecurityEvent::Other { event_type, data }
+            if event_type.ends_with("caep/event-type/session-revoked")
+                && data["subject"]["format"] == "opaque"
+    )));
+
+    let client = GenericRiscClient::from(
+        RiscClient::builder(AUDIENCE)
+            .custom_key_provider(TestKeyProvider::default())
+            .replay_cache(Arc::new(LruReplayCache::default()))
+            .build(),
+    );
+    assert!(client.verify_event_token(&replayed).is_ok());
+    assert_eq!(
+        client.verify_event_token(&replayed).map(|_| ()),
+        Err(Error::Replayed)
+    );
+    // an exp claim is still checked
+    claims["exp"] = (now() - 60).into();
+    assert_eq!(
+        client.verify_event_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::Expired)
+    );
+    // other clients still require one
+    let mut claims = id_claims();
+    claims.as_object_mut().unwrap().remove("exp");
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::MissingClaim(
+            "exp".to_string()
+        )))
+    );
+}
+
+// DER SubjectPublicKeyInfo of TEST_EC_PRIVATE_KEY, in the format of the Play Console
+#[cfg(feature = "play-integrity")]
+const TEST_EC_PUBLIC_KEY: &str = "MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEQZedbhNif7gLZE5m2uM3aJ7dipfoj55M0eUBEgUoLxUi02TxTC+sQacIfs6yf1V8woWNsek4z575+DGuCOft0Q==";
+#[cfg(feature = "play-integrity")]
+const TEST_AES_KEY: [u8; 32] = [7; 32];
+
+#[cfg(feature = "play-integrity")]
+fn encrypt_a256kw(plaintext: &str) -> String {
+    use ring::aead::{AES_256_GCM, Aad, LessSafeKey, Nonce, UnboundKey};
+    let content_key = [42; 32];
+    let iv = [9; 12];
+    let mut encrypted_key = [0; 40];
+    aes_kw::KekAes256::from(TEST_AES_KEY)
+        .wrap(&content_key, &mut encrypted_key)
+        .unwrap();
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"A256KW","enc":"A256GCM"}"#);
+    let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &content_key).unwrap());
+    let mut in_out = plaintext.as_bytes().to_vec();
+    let tag = key
+        .seal_in_place_separate_tag(
+            Nonce::assume_unique_for_key(iv),
+            Aad::from(header.as_bytes()),
+            &mut in_out,
+        )
+        .unwrap();
+    [
+        header,
+        URL_SAFE_NO_PAD.encode(encrypted_key),
+        URL_SAFE_NO_PAD.encode(iv),
+        URL_SAFE_NO_PAD.encode(in_out),
+        URL_SAFE_NO_PAD.encode(tag),
+    ]
+    .join(".")
+}
+
+#[cfg(feature = "play-integrity")]
+#[test]
+pub fn test_play_integrity_token() {
+    let verifier = PlayIntegrityVerifier::new(
+        "com.example.app",
+        &STANDARD.encode(TEST_AES_KEY),
+        TEST_EC_PUBLIC_KEY,
+    )
+    .unwrap();
+    let mut claims = serde_json::json!({
+        "requestDetails": {
+            "requestPackageName": "com.example.app",
+            "nonce": "aGVsbG8gd29ybGQgdGhlcmU",
+            "timestampMillis": (now() * 1000).to_string()
+        },
+        "appIntegrity": {
+            "appRecognitionVerdict": "PLAY_RECOGNIZED",
+            "packageName": "com.example.app",
+            "certificateSha256Digest": ["6a6a1474b5cbbb2b1aa57e0bc3"],
+            "versionCode": "42"
+        },
+        "deviceIntegrity": {
+            "deviceRecognitionVerdict": ["MEETS_DEVICE_INTEGRITY", "MEETS_BASIC_INTEGRITY"]
+        },
+        "accountDetails": {"appLicensingVerdict": "LICENSED"}
+    });
+    let token = encrypt_a256kw(&sign_token_es256(&claims));
+    let payload = verifier
+        .verify_token_with_nonce(&token, "aGVsbG8gd29ybGQgdGhlcmU")
+        .expect("integrity token should be valid");
+    assert_eq!(
+        payload.app_integrity.app_recognition_verdict,
+        AppRecognitionVerdict::PlayRecognized
+    );
+    assert!(
+        payload
+            .device_integrity
+            .device_recognition_verdict
+            .contains(&DeviceRecognitionVerdict::MeetsDeviceIntegrity)
+    );
+    assert_eq!(
+        payload.account_details.app_licensing_verdict,
+        AppLicensingVerdict::Licensed
+    );
+    assert_eq!(
+        verifier
+            .verify_token_with_nonce(&token, "other")
+            .map(|_| ()),
+        Err(Error::Nonce(NonceError::Unknown))
+    );
+
+    // signed with a different key
+    let other_key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+        &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+        &ring::rand::SystemRandom::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        verifier
+            .verify_token(&encrypt_a256kw(&sign_token_es256_with(
+                other_key.as_ref(),
+                &claims
+            )))
+            .map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::Crypto))
+    );
+    // signed with another algorithm
+    assert_eq!(
+        verifier
+            .verify_token(&encrypt_a256kw(&sign_token(&claims)))
+            .map(|_| ()),
+        Err(Error::UnsupportedAlgorithm(algorithm::Algorithm::RS256))
+    );
+    let mut tampered = token.into_bytes();
+    let last = tampered.len() - 1;
+    tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+    assert!(
+        verifier
+            .verify_token(std::str::from_utf8(&tampered).unwrap())
+            .is_err()
+    );
+
+    claims["requestDetails"]["timestampMillis"] = ((now() - 3600) * 1000).to_string().into();
+    let stale = encrypt_a256kw(&sign_token_es256(&claims));
+    assert_eq!(
+        verifier.verify_token(&stale).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "requestDetails.timestampMillis".to_string()
+        )))
+    );
+    let verifier = verifier.max_age(std::time::Duration::from_secs(2 * 3600));
+    assert!(verifier.verify_token(&stale).is_ok());
+
+    claims["requestDetails"]["requestPackageName"] = "com.example.other".into();
+    assert_eq!(
+        verifier
+            .verify_token(&encrypt_a256kw(&sign_token_es256(&claims)))
+            .map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "requestDetails.requestPackageName".to_string()
+        )))
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_oidc_signing_algorithms() {
+    const ISSUER: &str = "https://idp.example.com";
+    const JWKS_URI: &str = "https://idp.example.com/jwks";
+    // restore the key set from a snapshot instead of fetching it
+    let path = std::env::temp_dir().join(format!("oidc-snapshot-{}.json", std::process::id()));
+    let snapshot = serde_json::json!({
+        "url": JWKS_URI,
+        "keys": serde_json::from_str::<serde_json::Value>(JWKS).unwrap(),
+        "expires_at": now() + 3600,
+    });
+    std::fs::write(&path, snapshot.to_string()).unwrap();
+    let provider = OidcProvider::with_configuration(ProviderConfiguration {
+        issuer: ISSUER.to_string(),
+        jwks_uri: JWKS_URI.to_string(),
+        id_token_signing_alg_values_supported: vec![algorithm::Algorithm::RS256],
+    })
+    .snapshot_path(&path);
+    std::fs::remove_file(&path).unwrap();
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(provider)
+        .issuers(&[ISSUER])
+        .build();
+    let claims = claims(ISSUER, AUDIENCE, serde_json::json!({}));
+    assert!(client.verify_id_token(&sign_token(&claims)).is_ok());
+    // the ES256 key is in the key set, but the issuer does not sign ID tokens with it
+    assert_eq!(
+        client
+            .verify_id_token(&sign_token_es256(&claims))
+            .map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidKeyId))
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_entra_tenant_issuer() {
+    const TENANT: &str = "72f988bf-86f1-41af-91ab-2d7cd011db47";
+    let client = OidcClient::entra_builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    let mut claims = id_claims();
+    claims["iss"] = format!("https://login.microsoftonline.com/{TENANT}/v2.0").into();
+    claims["tid"] = TENANT.into();
+    let token = client
+        .verify_id_token(&sign_token(&claims))
+        .expect("entra token should be valid");
+    assert_eq!(token.payload.extra["tid"], TENANT);
+
+    let invalid_issuer = Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+        "iss".to_string(),
+    )));
+    claims["tid"] = "9188040d-6c67-4c5b-b112-36a304b66dad".into();
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        invalid_issuer
+    );
+    claims.as_object_mut().unwrap().remove("tid");
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        invalid_issuer
+    );
+
+    let client = OidcClient::entra_builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .tenants(&["9188040d-6c67-4c5b-b112-36a304b66dad"])
+        .build();
+    claims["tid"] = TENANT.into();
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "tid".to_string()
+        )))
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_apple_id_token() {
+    let client = GenericAppleClient::from(
+        AppleClient::builder("com.example.app")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let mut claims = apple_claims();
+    let token = client
+        .verify_id_token(&sign_token(&claims))
+        .expect("apple token should be valid");
+    assert_eq!(token.payload.email_verified, Some(true));
+    assert_eq!(token.payload.is_private_email, Some(true));
+    assert_eq!(
+        token.payload.real_user_status,
+        Some(RealUserStatus::LikelyReal)
+    );
+
+    claims["email_verified"] = false.into();
+    claims.as_object_mut().unwrap().remove("is_private_email");
+    let token = client.verify_id_token(&sign_token(&claims)).unwrap();
+    assert_eq!(token.payload.email_verified, Some(false));
+    assert_eq!(token.payload.is_private_email, None);
+
+    claims["real_user_status"] = 7.into();
+    let token = client.verify_id_token(&sign_token(&claims)).unwrap();
+    assert_eq!(
+        token.payload.real_user_status,
+        Some(RealUserStatus::Unrecognized(7))
+    );
+
+    claims["iss"] = "https://accounts.google.com".into();
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "iss".to_string()
+        )))
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_verifier_registry() {
+    let registry = VerifierRegistry::new()
+        .register_google(
+            Client::builder(AUDIENCE)
+                .custom_key_provider(TestKeyProvider::default())
+                .build(),
+        )
+        .register_firebase(
+            FirebaseClient::builder("test-project")
+                .custom_key_providers(TestKeyProvider::default(), TestKeyProvider::default())
+                .build(),
+        )
+        .register_apple(GenericAppleClient::from(
+            AppleClient::builder("com.example.app")
+                .custom_key_provider(TestKeyProvider::default())
+                .build(),
+        ))
+        // clients with other key providers
+        .register_oidc(
+            OidcClient::entra_builder(AUDIENCE)
+                .custom_key_provider(StaticKeyProvider::new(serde_json::from_str(JWKS).unwrap()))
+                .build(),
+        )
+        .register_risc(GenericRiscClient::from(
+            RiscClient::builder(AUDIENCE)
+                .custom_key_provider(TestKeyProvider::default())
+                .build(),
+        ));
+
+    assert!(matches!(
+        registry.verify(&sign_token(&id_claims())),
+        Ok(VerifiedToken::Google(_))
+    ));
+    let mut claims = firebase_claims();
+    assert!(matches!(
+        registry.verify(&sign_token(&claims)),
+        Ok(VerifiedToken::FirebaseIdToken(_))
+    ));
+    claims["iss"] = "https://session.firebase.google.com/test-project".into();
+    assert!(matches!(
+        registry.verify(&sign_token(&claims)),
+        Ok(VerifiedToken::FirebaseSessionCookie(_))
+    ));
+    match registry.verify(&sign_token(&apple_claims())) {
+        Ok(VerifiedToken::Apple(token)) => assert_eq!(token.payload.nonce_supported, Some(true)),
+        other => panic!("expected an Apple token, got {other:?}"),
+    }
+    assert!(matches!(
+        registry.verify(&sign_token(&security_event_claims())),
+        Ok(VerifiedToken::SecurityEvent(_))
+    ));
+
+    // routed by the tenant issuer template
+    const TENANT: &str = "72f988bf-86f1-41af-91ab-2d7cd011db47";
+    let mut entra_claims = id_claims();
+    entra_claims["iss"] = format!("https://login.microsoftonline.com/{TENANT}/v2.0").into();
+    entra_claims["tid"] = TENANT.into();
+    assert!(matches!(
+        registry.verify(&sign_token(&entra_claims)),
+        Ok(VerifiedToken::Oidc(_))
+    ));
+    entra_claims["tid"] = "9188040d-6c67-4c5b-b112-36a304b66dad".into();
+    assert_eq!(
+        registry.verify(&sign_token(&entra_claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "iss".to_string()
+        )))
+    );
+    entra_claims["iss"] = "https://login.microsoftonline.com/v2.0".into();
+    assert_eq!(
+        registry.verify(&sign_token(&entra_claims)).map(|_| ()),
+        Err(Error::UnknownIssuer(
+            "https://login.microsoftonline.com/v2.0".to_string()
+        ))
+    );
+
+    // routed by issuer, then verified with that issuer's policy
+    claims["aud"] = "other-project".into();
+    assert_eq!(
+        registry.verify(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "aud".to_string()
+        )))
+    );
+    claims["iss"] = "https://cloud.google.com/iap".into();
+    assert_eq!(
+        registry.verify(&sign_token(&claims)).map(|_| ()),
+        Err(Error::UnknownIssuer(
+            "https://cloud.google.com/iap".to_string()
+        ))
+    );
+}
+
+const ADMIN_CLIENT_ID: &str = "admin-console.apps.googleusercontent.com";
+
+fn admin_policy() -> ClientPolicy {
+    ClientPolicy::new()
+        .hosted_domain("example.com")
+        .email_policy(EmailPolicy::new().allow_domain("example.com"))
+        .max_age(std::time::Duration::from_secs(600))
+}
+
+fn admin_claims() -> serde_json::Value {
+    with(
+        id_claims(),
+        serde_json::json!({"aud": ADMIN_CLIENT_ID, "email": "admin@example.com", "hd": "example.com"}),
+    )
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_client_policies() {
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .client(ADMIN_CLIENT_ID, admin_policy())
+        .build();
+    let verify = |claims: serde_json::Value| {
+        client
+            .verify_id_token(&sign_token(&claims))
+            .map(|token| token.client_id)
+    };
+    assert_eq!(verify(id_claims()), Ok(AUDIENCE.to_string()));
+    assert_eq!(verify(admin_claims()), Ok(ADMIN_CLIENT_ID.to_string()));
+
+    let mut public_user = id_claims();
+    public_user["aud"] = ADMIN_CLIENT_ID.into();
+    assert_eq!(
+        verify(public_user.clone()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "hd".to_string()
+        )))
+    );
+    public_user["hd"] = "example.com".into();
+    assert_eq!(verify(public_user), Err(Error::EmailNotAllowed));
+
+    let mut stale = admin_claims();
+    stale["iat"] = (now() - 1200).into();
+    assert_eq!(verify(stale), Err(Error::TooOld));
+    // the maximum age does not overflow the issue time
+    let mut far_future = admin_claims();
+    far_future["iat"] = u64::MAX.into();
+    far_future["exp"] = u64::MAX.into();
+    assert_eq!(verify(far_future), Ok(ADMIN_CLIENT_ID.to_string()));
+
+    let mut other_client = id_claims();
+    other_client["aud"] = "other.apps.googleusercontent.com".into();
+    assert_eq!(
+        verify(other_client),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "aud".to_string()
+        )))
+    );
+}
+
+#[cfg(all(feature = "blocking", feature = "config"))]
+#[test]
+pub fn test_client_from_config() {
+    let config = VerifierConfig::from_json(&format!(
+        r#"{{"client_id": "{AUDIENCE}", "leeway_secs": 60,
+            "clients": {{"{ADMIN_CLIENT_ID}": {{"hosted_domains": ["example.com"]}}}}}}"#
+    ))
+    .unwrap();
+    let client = client::GenericClientBuilder::<std::cell::RefCell<key_provider::GoogleKeyProvider>>::from_config(&config)
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    let mut recently_expired = id_claims();
+    recently_expired["iat"] = (now() - 3600).into();
+    recently_expired["exp"] = (now() - 10).into();
+    let token = client.verify_id_token(&sign_token(&recently_expired));
+    assert_eq!(token.map(|token| token.client_id), Ok(AUDIENCE.to_string()));
+    recently_expired["exp"] = (now() - 120).into();
+    let token = client.verify_id_token(&sign_token(&recently_expired));
+    assert_eq!(token.map(|_| ()), Err(Error::Expired));
+    let token = client.verify_id_token(&sign_token(&admin_claims()));
+    assert_eq!(
+        token.map(|token| token.client_id),
+        Ok(ADMIN_CLIENT_ID.to_string())
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_policy_handle() {
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    let handle = client.policy_handle();
+    let admin_token = sign_token(&admin_claims());
+    assert!(client.verify_id_token(&admin_token).is_err());
+
+    handle.replace(Client::builder(AUDIENCE).client(ADMIN_CLIENT_ID, admin_policy()));
+    let token = client.verify_id_token(&admin_token);
+    assert_eq!(
+        token.map(|token| token.client_id),
+        Ok(ADMIN_CLIENT_ID.to_string())
+    );
+
+    handle.replace(
+        Client::builder(AUDIENCE).email_policy(EmailPolicy::new().allow_domain("example.com")),
+    );
+    let token = client.verify_id_token(&sign_token(&id_claims()));
+    assert_eq!(token.map(|_| ()), Err(Error::EmailNotAllowed));
+}
+
+#[cfg(all(feature = "blocking", feature = "config"))]
+#[test]
+pub fn test_config_watcher() {
+    let path = std::env::temp_dir().join(format!("verifier-{}.toml", std::process::id()));
+    // every rewrite keeps the same modification time, so only the length tells them apart
+    let modified = std::time::SystemTime::now();
+    let write = |contents: String| {
+        std::fs::write(&path, contents).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(modified).unwrap();
+    };
+    write(format!(
+        "client_id = \"{AUDIENCE}\"\nallowed_domains = [\"example.com\"]"
+    ));
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    let verify = || {
+        client
+            .verify_id_token(&sign_token(&id_claims()))
+            .map(|_| ())
+    };
+    let mut watcher = client.policy_handle().watch(&path);
+    assert_eq!(watcher.poll(), Ok(true));
+    assert_eq!(watcher.poll(), Ok(false));
+    assert_eq!(verify(), Err(Error::EmailNotAllowed));
+
+    write("client_id = \"\"".to_string());
+    assert_eq!(
+        watcher.poll(),
+        Err(Error::Config(ConfigError::Invalid(
+            "client_id is empty".to_string()
+        )))
+    );
+    assert_eq!(verify(), Err(Error::EmailNotAllowed));
+
+    write(format!("client_id = \"{AUDIENCE}\""));
+    assert_eq!(watcher.poll(), Ok(true));
+    assert_eq!(verify(), Ok(()));
+
+    drop(client);
+    assert!(watcher.poll().is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(all(feature = "blocking", feature = "config"))]
+#[test]
+pub fn test_reload_keeps_builder_settings() {
+    const SERVICE_URL: &str = "https://service.example.com/tasks";
+    let client = Client::builder(SERVICE_URL)
+        .custom_key_provider(TestKeyProvider::default())
+        .audience_url_prefix()
+        .service_accounts(&["*@test-project.iam.gserviceaccount.com"])
+        .build();
+    let handle = client.policy_handle();
+    let verify = |email: &str| {
+        let mut claims = id_claims();
+        claims["aud"] = format!("{SERVICE_URL}/run").into();
+        claims["email"] = email.into();
+        client.verify_id_token(&sign_token(&claims)).map(|_| ())
+    };
+    let service_account = "tasks@test-project.iam.gserviceaccount.com";
+    let config = |toml: &str| VerifierConfig::from_toml(toml).unwrap();
+    let url_only = config(&format!("client_id = \"{SERVICE_URL}\""));
+
+    handle.reload(&url_only);
+    assert_eq!(verify(service_account), Ok(()));
+    assert_eq!(verify(EMAIL), Err(Error::EmailNotAllowed));
+
+    let gmail = format!("client_id = \"{SERVICE_URL}\"\nallowed_domains = [\"gmail.com\"]");
+    handle.reload(&config(&gmail));
+    assert_eq!(verify(EMAIL), Ok(()));
+    // an email policy left out of the next configuration is the builder's again
+    handle.reload(&url_only);
+    assert_eq!(verify(EMAIL), Err(Error::EmailNotAllowed));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_static_key_provider() {
+    let keys: JsonWebKeySet = serde_json::from_str(JWKS).unwrap();
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(StaticKeyProvider::new(keys))
+        .build();
+    assert!(client.verify_id_token(&sign_token(&id_claims())).is_ok());
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_file_key_provider() {
+    let path = std::env::temp_dir().join(format!("jwks-{}.json", std::process::id()));
+    // every rewrite keeps the same modification time, so only the length tells them apart
+    let modified = std::time::SystemTime::now();
+    let write = |contents: &str| {
+        std::fs::write(&path, contents).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(modified).unwrap();
+    };
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(FileKeyProvider::new(&path))
+        .build();
+    let verify = || {
+        client
+            .verify_id_token(&sign_token(&id_claims()))
+            .map(|_| ())
+    };
+    assert!(matches!(
+        verify(),
+        Err(Error::RetrieveKeyFailure(KeyProviderError::Io(..)))
+    ));
+
+    write(r#"{"keys": []}"#);
+    assert_eq!(
+        verify(),
+        Err(Error::InvalidToken(error::InvalidError::InvalidKeyId))
+    );
+    write(JWKS);
+    assert_eq!(verify(), Ok(()));
+    // a file being rewritten keeps the previous keys
+    write("{");
+    assert_eq!(verify(), Ok(()));
+    std::fs::remove_file(&path).unwrap();
+}
+
+fn missing_key_file() -> FileKeyProvider {
+    FileKeyProvider::new("/nonexistent/jwks.json")
+}
+
+fn static_keys(jwks: &str) -> StaticKeyProvider {
+    StaticKeyProvider::new(serde_json::from_str(jwks).unwrap())
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_fallback_provider() {
+    let mut provider = FallbackProvider::new(missing_key_file(), static_keys(JWKS));
+    assert!(provider.get_key("test-key").unwrap().is_some());
+    let mut provider = FallbackProvider::new(static_keys(r#"{"keys": []}"#), static_keys(JWKS));
+    assert!(matches!(provider.get_key("test-key"), Ok(None)));
+
+    let mut provider = FallbackProvider::new(
+        missing_key_file(),
+        FallbackProvider::new(missing_key_file(), missing_key_file()),
+    );
+    match provider.get_key("test-key") {
+        Err(KeyProviderError::Sources(errors)) => {
+            assert_eq!(errors.len(), 3);
+            assert!(matches!(errors[0], KeyProviderError::Io(..)));
+        }
+        result => panic!("unexpected {result:?}"),
+    }
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_merged_provider() {
+    let mut provider = MergedProvider::new(static_keys(r#"{"keys": []}"#), static_keys(JWKS));
+    assert!(provider.get_key("test-key").unwrap().is_some());
+    let mut provider = MergedProvider::new(missing_key_file(), static_keys(JWKS));
+    assert!(provider.get_key("test-key").unwrap().is_some());
+    assert!(matches!(
+        provider.get_key("unknown"),
+        Err(KeyProviderError::Sources(errors)) if errors.len() == 1
+    ));
+
+    // the first key set wins for a duplicate key ID
+    let other_key = JWKS.replace("09bcf8028e06537d4d3ae4d84f5c5babcf2c0f0a", "test-key");
+    let client = Client::builder(AUDIENCE)
+        .custom_key_provider(MergedProvider::new(
+            static_keys(&other_key),
+            static_keys(JWKS),
+        ))
+        .build();
+    let result = client.verify_id_token(&sign_token(&id_claims()));
+    assert_eq!(
+        result.map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::Crypto))
+    );
+}
+
+fn instance_identity_claims() -> serde_json::Value {
+    claims(
+        "https://accounts.google.com",
+        "https://attestation.example.com",
+        serde_json::json!({
+            "email": "123456789-compute@developer.gserviceaccount.com",
+            "email_verified": true,
+            "google": {
+                "compute_engine": {
+                    "instance_creation_timestamp": now() - 3600,
+                    "instance_id": "4123456789012345678",
+                    "instance_name": "node-1",
+                    "license_id": ["1000010"],
+                    "project_id": "test-project",
+                    "project_number": 123456789,
+                    "zone": "us-central1-a"
+                }
+            }
+        }),
+    )
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_instance_identity() {
+    let client = GenericInstanceIdentityClient::from(
+        InstanceIdentityClient::builder("https://attestation.example.com")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    )
+    .allow_projects(&["test-project"])
+    .allow_zones(&["us-central1-a", "us-central1-b"]);
+    let mut claims = instance_identity_claims();
+    let token = client
+        .verify_instance_identity(&sign_token(&claims))
+        .expect("instance token should be valid");
+    let instance = &token.payload.google.compute_engine;
+    assert_eq!(instance.instance_name, "node-1");
+    assert_eq!(instance.project_number, 123456789);
+    assert_eq!(instance.license_id, ["1000010"]);
+
+    claims["google"]["compute_engine"]["zone"] = "europe-west1-b".into();
+    assert_eq!(
+        client
+            .verify_instance_identity(&sign_token(&claims))
+            .map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "google.compute_engine.zone".to_string()
+        )))
+    );
+    claims["google"]["compute_engine"]["zone"] = "us-central1-b".into();
+    claims["google"]["compute_engine"]["project_id"] = "other-project".into();
+    assert_eq!(
+        client
+            .verify_instance_identity(&sign_token(&claims))
+            .map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "google.compute_engine.project_id".to_string()
+        )))
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+pub fn test_service_account_caller() {
+    let client = Client::builder("https://service-abc.a.run.app")
+        .custom_key_provider(TestKeyProvider::default())
+        .audience_url_prefix()
+        .service_accounts(&["*@test-project.iam.gserviceaccount.com"])
+        .build();
+    let mut claims = id_claims();
+    claims["aud"] = "https://service-abc.a.run.app/tasks/run".into();
+    claims["email"] = "scheduler@test-project.iam.gserviceaccount.com".into();
+    assert!(client.verify_id_token(&sign_token(&claims)).is_ok());
+
+    claims["email"] = "scheduler@other-project.iam.gserviceaccount.com".into();
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::EmailNotAllowed)
+    );
+    claims["email"] = "scheduler@test-project.iam.gserviceaccount.com".into();
+    claims["aud"] = "https://service-abc.a.run.app.example.com".into();
+    assert_eq!(
+        client.verify_id_token(&sign_token(&claims)).map(|_| ()),
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "aud".to_string()
+        )))
+    );
+
+    claims["aud"] = AUDIENCE.into();
+    let token = sign_token(&claims);
+    let verify = |builder: crate::client::GenericClientBuilder<std::cell::RefCell<_>>| {
+        builder
+            .custom_key_provider(TestKeyProvider::default())
+            .build()
+            .verify_id_token(&token)
+            .map(|_| ())
+    };
+    assert_eq!(
+        verify(Client::builder(AUDIENCE).service_accounts(&[])),
+        Err(Error::EmailNotAllowed)
+    );
+    // added to an explicit email policy rather than replacing it
+    let policy = EmailPolicy::new().deny_email("scheduler@test-project.iam.gserviceaccount.com");
+    assert_eq!(
+        verify(
+            Client::builder(AUDIENCE)
+                .email_policy(policy)
+                .service_accounts(&["*@test-project.iam.gserviceaccount.com"])
+        ),
+        Err(Error::EmailNotAllowed)
+    );
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn decode_keys_async() {
+    for kid in KIDS.iter() {
+        TestKeyProvider::default().get_key_async(kid).await.unwrap();
+    }
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_file_key_provider_async() {
+    let path = std::env::temp_dir().join(format!("jwks-async-{}.json", std::process::id()));
+    let mut provider = FileKeyProvider::new(&path);
+    assert!(matches!(
+        provider.get_key_async("test-key").await,
+        Err(KeyProviderError::Io(..))
+    ));
+    std::fs::write(&path, JWKS).unwrap();
+    assert!(provider.get_key_async("test-key").await.unwrap().is_some());
+    std::fs::remove_file(&path).unwrap();
+    // the keys read before outlive the file
+    assert!(provider.get_key_async("test-key").await.unwrap().is_some());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_invalid_jwks_uri_async() {
+    let configuration: ProviderConfiguration = serde_json::from_str(
+        r#"{"issuer": "https://idp.example.com", "jwks_uri": "https://idp example.com/certs"}"#,
+    )
+    .unwrap();
+    let mut provider = OidcProvider::with_configuration(configuration);
+    assert!(matches!(
+        provider.get_key_async("test-key").await,
+        Err(KeyProviderError::Fetch(..))
+    ));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_client_async() {
+    let client = TokioClient::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    assert_eq!(
+        client.verify_token_async(TOKEN).await.map(|_| ()),
+        Err(Error::Expired)
+    );
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_client_invalid_client_id_async() {
+    let client = TokioClient::builder("invalid client id")
+        .custom_key_provider(TestKeyProvider::default())
+        .build();
+    let result = client.verify_token_async(TOKEN).await.map(|_| ());
+    assert_eq!(
+        result,
+        Err(Error::InvalidToken(error::InvalidError::InvalidClaims(
+            "aud".to_string()
+        )))
+    );
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_id_token_async() {
+    let client = TokioClient::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .unsafe_ignore_expiration()
+        .build();
+    let id_token = client
+        .verify_id_token_async(TOKEN)
+        .await
+        .expect("id token should be valid");
+    assert_eq!(id_token.claims.audience, AUDIENCE);
+    assert_eq!(id_token.payload.domain, None);
+    assert_eq!(id_token.payload.email, Some(EMAIL.to_string()));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_nonce_async() {
+    let store = Arc::new(MemoryNonceStore::new(std::time::Duration::from_secs(60)));
+    let client = TokioClient::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .nonce_store(store.clone())
+        .build();
+    let mut claims = id_claims();
+    claims["nonce"] = "not issued".into();
+    assert_eq!(
+        client
+            .verify_id_token_async(&sign_token(&claims))
+            .await
+            .map(|_| ()),
+        Err(Error::Nonce(NonceError::Unknown))
+    );
+    claims["nonce"] = store.issue().unwrap().into();
+    let token = sign_token(&claims);
+    assert!(client.verify_id_token_async(&token).await.is_ok());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_nonce_with_access_token_async() {
+    let store = Arc::new(MemoryNonceStore::new(std::time::Duration::from_secs(60)));
+    let client = TokioClient::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .nonce_store(store.clone())
+        .build();
+    let mut claims = id_claims();
+    claims["at_hash"] = "77QmUPtjPfzWtF2AnpK9RQ".into();
+    claims["nonce"] = store.issue().unwrap().into();
+    let token = sign_token(&claims);
+    let access_token = "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y";
+    assert!(
+        client
+            .verify_id_token_with_access_token_async(&token, access_token)
+            .await
+            .is_ok()
+    );
+    assert_eq!(
+        client
+            .verify_id_token_with_access_token_async(&token, access_token)
+            .await
+            .map(|_| ()),
+        Err(Error::Nonce(NonceError::Used))
+    );
+}
+
+// the preset clients share their checks with the blocking ones, so one token each is enough
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_preset_clients_async() {
+    let client = TokioFirebaseClient::builder("test-project")
+        .custom_key_providers(TestKeyProvider::default(), TestKeyProvider::default())
+        .build();
+    let token = client
+        .verify_id_token_async(&sign_token(&firebase_claims()))
+        .await
+        .expect("firebase token should be valid");
+    assert_eq!(token.payload.user_id.as_deref(), Some("firebase-user"));
+
+    let audience = app_engine_audience("1234", "test-project");
+    let client = GenericIapClient::from(
+        TokioIapClient::builder(&audience)
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let claims = claims(
+        "https://cloud.google.com/iap",
+        "/projects/1234/apps/test-project",
+        serde_json::json!({"email": EMAIL, "hd": "example.com"}),
+    );
+    let token = client
+        .verify_assertion_async(&sign_token_es256(&claims))
+        .await
+        .expect("IAP assertion should be valid");
+    assert_eq!(token.payload.domain.as_deref(), Some("example.com"));
+
+    let client = GenericAppCheckClient::from(
+        TokioAppCheckClient::builder("123456789")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let token = client
+        .verify_token_async(&sign_token(&app_check_claims()))
+        .await
+        .expect("app check token should be valid");
+    assert_eq!(token.payload.app_id, "1:123456789:web:abcdef");
+
+    let client = GenericRiscClient::from(
+        TokioRiscClient::builder(AUDIENCE)
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let token = client
+        .verify_event_token_async(&sign_token(&security_event_claims()))
+        .await
+        .expect("SET without exp should be valid");
+    assert_eq!(token.payload.events.len(), 2);
+
+    let client = GenericAppleClient::from(
+        TokioAppleClient::builder("com.example.app")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    );
+    let token = client
+        .verify_id_token_async(&sign_token(&apple_claims()))
+        .await
+        .expect("apple token should be valid");
+    assert_eq!(
+        token.claims.subject.as_deref(),
+        Some("001234.abcdef0123456789.1234")
+    );
+
+    let client = GenericInstanceIdentityClient::from(
+        TokioInstanceIdentityClient::builder("https://attestation.example.com")
+            .custom_key_provider(TestKeyProvider::default())
+            .build(),
+    )
+    .allow_projects(&["test-project"]);
+    let token = client
+        .verify_instance_identity_async(&sign_token(&instance_identity_claims()))
+        .await
+        .expect("instance token should be valid");
+    assert_eq!(token.payload.google.compute_engine.zone, "us-central1-a");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_verifier_registry_async() {
+    let registry = TokioVerifierRegistry::new()
+        .register_google(
+            TokioClient::builder(AUDIENCE)
+                .custom_key_provider(TestKeyProvider::default())
+                .build(),
+        )
+        .register_iap(GenericIapClient::from(
+            TokioIapClient::builder("/projects/1234/apps/test-project")
+                .custom_key_provider(TestKeyProvider::default())
+                .build(),
+        ));
+    assert!(matches!(
+        registry.verify_async(&sign_token(&id_claims())).await,
+        Ok(VerifiedToken::Google(_))
+    ));
+    assert!(matches!(
+        registry.verify_async(&sign_token(&apple_claims())).await,
+        Err(Error::UnknownIssuer(_))
+    ));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_client_policies_async() {
+    let client = TokioClient::builder(AUDIENCE)
+        .custom_key_provider(TestKeyProvider::default())
+        .client(ADMIN_CLIENT_ID, admin_policy())
+        .build();
+    let token = client
+        .verify_id_token_async(&sign_token(&admin_claims()))
+        .await
+        .expect("admin token should be valid");
+    assert_eq!(token.client_id, ADMIN_CLIENT_ID);
+    let mut stale = admin_claims();
+    stale["iat"] = (now() - 1200).into();
+    let result = client.verify_id_token_async(&sign_token(&stale)).await;
+    assert_eq!(result.map(|_| ()), Err(Error::TooOld));
+
+    client
+        .policy_handle()
+        .replace(TokioClient::builder(AUDIENCE));
+    let result = client
+        .verify_id_token_async(&sign_token(&admin_claims()))
+        .await;
+    assert!(result.is_err());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_composite_providers_async() {
+    let provider = MergedProvider::new(
+        FallbackProvider::new(missing_key_file(), static_keys(r#"{"keys": []}"#)),
+        static_keys(JWKS),
+    );
+    let client = TokioClient::builder(AUDIENCE)
+        .custom_key_provider(provider)
+        .build();
+    let token = client
+        .verify_id_token_async(&sign_token(&id_claims()))
+        .await;
+    assert!(token.is_ok());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_deadlock_prevention() {
+    let client = TokioClient::builder(AUDIENCE)
+        .unsafe_ignore_expiration()
+        .build();
+    join_all((0..10u8).map(|_| verify_token_async(&client))).await;
+}
+
+#[cfg(feature = "async")]
+async fn verify_token_async(client: &TokioClient) {
+    let result = client.verify_token_async(TOKEN).await;
+    assert!(matches!(
+        result,
+        Err(Error::InvalidToken(error::InvalidError::Json(_)))
+    ));
+    // verify_token_async expects an empty payload, which serde_json tries to parse as '{}'
+    // therefore the token is considered invalid due to failed json parsing:
+    // invalid type: map, expected unit at line 1 column 0
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_verifier_config() {
+    let config = VerifierConfig::from_toml(
+        r#"
+        client_id = "public.apps.googleusercontent.com"
+        allowed_domains = ["example.com"]
+        leeway_secs = 30
+        replay_cache_capacity = 1000
+
+        [clients."admin.apps.googleusercontent.com"]
+        hosted_domains = ["example.com"]
+        max_age_secs = 600
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.leeway_secs, 30);
+    assert_eq!(
+        config.clients["admin.apps.googleusercontent.com"].max_age_secs,
+        Some(600)
+    );
+    let json = r#"{"client_id": "public.apps.googleusercontent.com", "leeway_secs": 30,
+        "allowed_domains": ["example.com"], "replay_cache_capacity": 1000,
+        "clients": {"admin.apps.googleusercontent.com":
+            {"hosted_domains": ["example.com"], "max_age_secs": 600}}}"#;
+    assert_eq!(VerifierConfig::from_json(json), Ok(config));
+
+    let vars = [
+        ("GOOGLE_JWT_CLIENT_ID", "public.apps.googleusercontent.com"),
+        ("GOOGLE_JWT_ALLOWED_DOMAINS", "example.com, example.org"),
+        ("GOOGLE_JWT_LEEWAY_SECS", "30"),
+        ("PATH", "/usr/bin"),
+    ];
+    let vars = vars.map(|(name, value)| (name.into(), value.into()));
+    let config = VerifierConfig::from_vars(vars.clone()).unwrap();
+    assert_eq!(config.allowed_domains, ["example.com", "example.org"]);
+    assert_eq!(config.leeway_secs, 30);
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        let not_unicode = || std::ffi::OsString::from_vec(vec![0xff]);
+        let other = (not_unicode(), not_unicode());
+        let with_other = vars.iter().cloned().chain([other]);
+        assert_eq!(VerifierConfig::from_vars(with_other), Ok(config));
+        let leeway = ("GOOGLE_JWT_LEEWAY_SECS".into(), not_unicode());
+        assert_eq!(
+            VerifierConfig::from_vars([leeway]),
+            Err(Error::Config(ConfigError::Parse(
+                "GOOGLE_JWT_LEEWAY_SECS is not valid unicode".to_owned()
+            )))
+        );
+    }
+
+    let invalid = |message: &str| Err(Error::Config(ConfigError::Invalid(message.to_owned())));
+    assert_eq!(
+        VerifierConfig::from_vars([("GOOGLE_JWT_LEEWAY".into(), "30".into())]),
+        invalid("unknown variable GOOGLE_JWT_LEEWAY")
+    );
+    assert_eq!(
+        VerifierConfig::from_toml("allowed_domains = [\"example.com\"]"),
+        invalid("client_id is empty")
+    );
+    assert_eq!(
+        VerifierConfig::from_toml("client_id = \"a\"\nallow_unverified_email = true"),
+        invalid("allow_unverified_email has no effect without allowed_domains or allowed_emails")
+    );
+    assert!(matches!(
+        VerifierConfig::from_toml("client_id = \"a\"\nleeway = 30"),
+        Err(Error::Config(ConfigError::Parse(_)))
     ));
-    // verify_token_async expects an empty payload, which serde_json tries to parse as '{}'
-    // therefore the token is considered invalid due to failed json parsing:
-    // invalid type: map, expected unit at line 1 column 0
 }
@@ -1,9 +1,15 @@
+use serde::{Deserialize, Deserializer};
 use serde_derive::Deserialize;
+use serde_json::{Map, Value};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token<P> {
     pub claims: RequiredClaims,
     pub payload: P,
+    /// The decoded JSON payload exactly as it was signed
+    pub raw_payload: String,
+    /// The configured client ID the audience matched
+    pub client_id: String,
 }
 
 // https://datatracker.ietf.org/doc/html/rfc7519#section-4.1
@@ -12,19 +18,54 @@ pub struct RequiredClaims {
     #[serde(rename = "iss")]
     pub issuer: String,
 
+    /// Only absent from tokens of clients that accept them, such as those signed by Google
+    /// service accounts
     #[serde(rename = "sub")]
-    pub subject: String,
+    pub subject: Option<String>,
 
-    #[serde(rename = "aud")]
+    /// The `aud` claim, or the entry of an `aud` array that matched the client
+    #[serde(rename = "aud", deserialize_with = "first_audience")]
     pub audience: String,
 
+    /// Only absent from tokens of clients that accept them, such as security event tokens
     #[serde(rename = "exp")]
-    pub expires_at: u64,
+    pub expires_at: Option<u64>,
 
     #[serde(rename = "iat")]
     pub issued_at: u64,
+
+    #[serde(rename = "nbf", default)]
+    pub not_before: Option<u64>,
+
+    #[serde(rename = "jti", default)]
+    pub jwt_id: Option<String>,
 }
 
+/// The `aud` claim, either a single string or an array of strings
+pub(crate) fn audiences<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(serde_derive::Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(aud) => vec![aud],
+        OneOrMany::Many(auds) => auds,
+    })
+}
+
+fn first_audience<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    audiences(deserializer)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| serde::de::Error::invalid_length(0, &"at least one audience"))
+}
+
+const REGISTERED_CLAIMS: [&str; 7] = ["iss", "sub", "aud", "exp", "iat", "nbf", "jti"];
+
 // https://developers.google.com/identity/gsi/web/reference/html-reference#credential
 #[allow(dead_code)]
 #[derive(Deserialize, Clone, Debug)]
@@ -38,4 +79,20 @@ pub struct IdPayload {
     pub locale: Option<String>,
     #[serde(rename = "hd")]
     pub domain: Option<String>,
+    pub azp: Option<String>,
+    pub nonce: Option<String>,
+    pub at_hash: Option<String>,
+    pub c_hash: Option<String>,
+    pub auth_time: Option<u64>,
+    /// Claims not covered by [RequiredClaims] or the fields above
+    #[serde(flatten, deserialize_with = "other_claims")]
+    pub extra: Map<String, Value>,
+}
+
+fn other_claims<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Map<String, Value>, D::Error> {
+    let mut claims = Map::deserialize(deserializer)?;
+    claims.retain(|name, _| !REGISTERED_CLAIMS.contains(&name.as_str()));
+    Ok(claims)
 }
@@ -1,15 +1,145 @@
+use std::collections::HashMap;
 #[cfg(feature = "async")]
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use ring::digest;
 use serde::Deserialize;
+use serde_derive::Deserialize;
 
-use crate::error::InvalidError::{InvalidClaims, InvalidKeyId, TokenFormat};
+use crate::algorithm::Algorithm;
+use crate::client_policy::ClientPolicy;
+use crate::error::InvalidError::{InvalidClaims, InvalidKeyId, MissingClaim, TokenFormat};
+use crate::error::KeyProviderError;
 #[cfg(feature = "async")]
 use crate::key_provider::AsyncKeyProvider;
 #[cfg(feature = "blocking")]
 use crate::key_provider::KeyProvider;
-use crate::{Error, RequiredClaims, Token, base64_decode, header::Header, jwk::JsonWebKey};
+use crate::{
+    Error, RequiredClaims, Token, base64_decode, base64_encode, header::Header, jwk::JsonWebKey,
+};
+
+pub const GOOGLE_ISSUERS: [&str; 2] = ["https://accounts.google.com", "accounts.google.com"];
+
+/// Stands for the `tid` claim in multi-tenant issuers, such as Microsoft Entra's
+/// `https://login.microsoftonline.com/{tenantid}/v2.0`
+pub const TENANT_PLACEHOLDER: &str = "{tenantid}";
+
+#[derive(Deserialize)]
+struct AudienceClaims {
+    #[serde(deserialize_with = "crate::token::audiences")]
+    aud: Vec<String>,
+    azp: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TenantClaim {
+    tid: Option<String>,
+}
+
+/// Issuers may contain a [TENANT_PLACEHOLDER], which stands for the `tid` claim.
+fn issuer_matches(issuers: &[String], issuer: &str, tenant: Option<&str>) -> bool {
+    issuers
+        .iter()
+        .any(|expected| match expected.split_once(TENANT_PLACEHOLDER) {
+            Some((prefix, suffix)) => tenant.is_some_and(|tid| {
+                issuer
+                    .strip_prefix(prefix)
+                    .and_then(|rest| rest.strip_suffix(suffix))
+                    == Some(tid)
+            }),
+            None => expected == issuer,
+        })
+}
+
+/// How the `aud` claim is compared with the expected audience
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AudienceMatch {
+    Exact,
+    /// The audience is a service URL, and tokens for any path below it are accepted
+    UrlPrefix,
+}
+
+/// Claim checks performed before the token signature is verified
+#[derive(Clone, Debug)]
+pub struct Validation {
+    pub audience: String,
+    pub audience_match: AudienceMatch,
+    pub issuers: Vec<String>,
+    pub check_expiration: bool,
+    /// Seconds of clock skew tolerated when checking `exp` and `nbf`
+    pub leeway: u64,
+    /// Reject tokens without an `exp` claim
+    pub require_expiration: bool,
+    /// Reject tokens without a `sub` claim
+    pub require_subject: bool,
+    /// Accept an `aud` array with several entries without an `azp` claim for the client
+    pub allow_multiple_audiences: bool,
+    /// Accepted `tid` claims; any tenant is accepted if empty
+    pub tenants: Vec<String>,
+    /// Other client IDs accepted as exact audiences, with their own rules
+    pub clients: HashMap<String, ClientPolicy>,
+}
+
+impl Validation {
+    pub fn new(audience: &str) -> Self {
+        Self {
+            audience: audience.to_owned(),
+            audience_match: AudienceMatch::Exact,
+            issuers: GOOGLE_ISSUERS.iter().map(|iss| iss.to_string()).collect(),
+            check_expiration: true,
+            leeway: 0,
+            require_expiration: true,
+            require_subject: true,
+            allow_multiple_audiences: false,
+            tenants: vec![],
+            clients: HashMap::new(),
+        }
+    }
+
+    /// The configured client ID matching `audience`, and the issuers accepted for it
+    fn matching_client(&self, audience: &str) -> Option<(&str, &[String])> {
+        if self.audience_matches(audience) {
+            return Some((&self.audience, &self.issuers));
+        }
+        let (client_id, policy) = self.clients.get_key_value(audience)?;
+        Some((
+            client_id,
+            policy.issuers.as_deref().unwrap_or(&self.issuers),
+        ))
+    }
+
+    fn audience_matches(&self, audience: &str) -> bool {
+        match self.audience_match {
+            AudienceMatch::Exact => audience == self.audience,
+            AudienceMatch::UrlPrefix => {
+                let prefix = self.audience.trim_end_matches('/');
+                audience.strip_prefix(prefix).is_some_and(|rest| {
+                    rest.is_empty() || rest.starts_with('/') || rest.starts_with('?')
+                })
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct IssuerClaim {
+    iss: String,
+}
+
+/// The issuer of a token with a key ID, read without any verification to decide how to verify it.
+pub fn peek_issuer(token_string: &str) -> Result<String, Error> {
+    let mut segments = token_string.split('.');
+    let (Some(encoded_header), Some(encoded_payload)) = (segments.next(), segments.next()) else {
+        return Err(Error::InvalidToken(TokenFormat("payload".to_string())));
+    };
+    let header: Header = serde_json::from_slice(&base64_decode(encoded_header)?)?;
+    if header.key_id.is_empty() {
+        return Err(Error::InvalidToken(InvalidKeyId));
+    }
+    let IssuerClaim { iss } = serde_json::from_slice(&base64_decode(encoded_payload)?)?;
+    Ok(iss)
+}
 
 #[derive(Debug)]
 pub struct UnverifiedToken<P> {
@@ -17,18 +147,16 @@ pub struct UnverifiedToken<P> {
     signed_body: String,
     signature: Vec<u8>,
     claims: RequiredClaims,
+    raw_payload: String,
     json_payload: P,
+    client_id: String,
 }
 
 impl<P> UnverifiedToken<P>
 where
     for<'a> P: Deserialize<'a> + std::fmt::Debug,
 {
-    pub fn validate(
-        token_string: &str,
-        check_expiration: bool,
-        client_id: &str,
-    ) -> Result<Self, Error> {
+    pub fn validate(token_string: &str, validation: &Validation) -> Result<Self, Error> {
         let mut segments = token_string.split('.');
         let encoded_header = segments
             .next()
@@ -44,22 +172,56 @@ where
         let signed_body = format!("{}.{}", encoded_header, encoded_payload);
         let signature = base64_decode(encoded_signature)?;
         let payload = base64_decode(encoded_payload)?;
-        let claims: RequiredClaims = serde_json::from_slice(&payload)?;
-        if claims.audience != client_id {
-            return Err(Error::InvalidToken(InvalidClaims("aud".to_string())));
-        }
-        if claims.issuer != "https://accounts.google.com" && claims.issuer != "accounts.google.com"
+        let mut claims: RequiredClaims = serde_json::from_slice(&payload)?;
+        let AudienceClaims { aud, azp } = serde_json::from_slice(&payload)?;
+        let (audience, (client_id, issuers)) = aud
+            .iter()
+            .find_map(|aud| Some((aud, validation.matching_client(aud)?)))
+            .ok_or(Error::InvalidToken(InvalidClaims("aud".to_string())))?;
+        // https://openid.net/specs/openid-connect-core-1_0.html#IDTokenValidation
+        if aud.len() > 1
+            && !validation.allow_multiple_audiences
+            && azp.as_deref() != Some(client_id)
         {
+            return Err(Error::InvalidToken(InvalidClaims("azp".to_string())));
+        }
+        claims.audience = audience.clone();
+        if validation.require_subject && claims.subject.is_none() {
+            return Err(Error::InvalidToken(MissingClaim("sub".to_string())));
+        }
+        let TenantClaim { tid } = serde_json::from_slice(&payload)?;
+        if !issuer_matches(issuers, &claims.issuer, tid.as_deref()) {
             return Err(Error::InvalidToken(InvalidClaims("iss".to_string())));
         }
+        let client_id = client_id.to_owned();
+        if !validation.tenants.is_empty()
+            && !tid.is_some_and(|tid| validation.tenants.contains(&tid))
+        {
+            return Err(Error::InvalidToken(InvalidClaims("tid".to_string())));
+        }
+        if validation.require_expiration && claims.expires_at.is_none() {
+            return Err(Error::InvalidToken(MissingClaim("exp".to_string())));
+        }
+        let check_expiration = validation.check_expiration;
         let current_timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        if check_expiration && claims.expires_at < current_timestamp {
+        if check_expiration
+            && claims
+                .expires_at
+                .is_some_and(|exp| exp.saturating_add(validation.leeway) < current_timestamp)
+        {
             return Err(Error::Expired);
         }
-        if claims.issued_at > claims.expires_at {
+        if check_expiration
+            && claims
+                .not_before
+                .is_some_and(|nbf| nbf > current_timestamp.saturating_add(validation.leeway))
+        {
+            return Err(Error::InvalidToken(InvalidClaims("nbf".to_string())));
+        }
+        if claims.expires_at.is_some_and(|exp| claims.issued_at > exp) {
             return Err(Error::InvalidToken(InvalidClaims("iat > exp".to_string())));
         }
         let json_payload: P = serde_json::from_slice(&payload)?;
@@ -67,13 +229,30 @@ where
             claims,
             signature,
             signed_body,
+            raw_payload: String::from_utf8_lossy(&payload).into_owned(),
             json_payload,
             header,
+            client_id,
         })
     }
 }
 
 impl<P> UnverifiedToken<P> {
+    pub fn algorithm(&self) -> Algorithm {
+        self.header.algorithm
+    }
+    /// The `jti` claim, or a hash of the signed header and payload for tokens without one.
+    ///
+    /// The signature is not hashed, since an ECDSA signature can be altered without
+    /// invalidating it.
+    pub fn token_id(&self) -> String {
+        match self.claims.jwt_id {
+            Some(ref jti) => jti.clone(),
+            None => {
+                base64_encode(digest::digest(&digest::SHA256, self.signed_body.as_bytes()).as_ref())
+            }
+        }
+    }
     #[cfg(feature = "blocking")]
     pub fn verify<KP: KeyProvider>(
         self,
@@ -90,16 +269,35 @@ impl<P> UnverifiedToken<P> {
         let key_id = self.header.key_id.clone();
         self.verify_with_key(key_provider.lock().await.get_key_async(&key_id).await)
     }
-    fn verify_with_key(self, key: Result<Option<JsonWebKey>, ()>) -> Result<Token<P>, Error> {
+    fn verify_with_key(
+        self,
+        key: Result<Option<JsonWebKey>, KeyProviderError>,
+    ) -> Result<Token<P>, Error> {
         let key = match key {
             Ok(Some(key)) => key,
             Ok(None) => return Err(Error::InvalidToken(InvalidKeyId)),
-            Err(_) => return Err(Error::RetrieveKeyFailure),
+            Err(e) => return Err(Error::RetrieveKeyFailure(e)),
         };
+        if key.algorithm() != self.header.algorithm {
+            return Err(Error::UnsupportedAlgorithm(self.header.algorithm));
+        }
         key.verify(self.signed_body.as_bytes(), &self.signature)?;
         Ok(Token {
             claims: self.claims,
             payload: self.json_payload,
+            raw_payload: self.raw_payload,
+            client_id: self.client_id,
         })
     }
 }
+
+#[test]
+fn test_audience_url_prefix() {
+    let mut validation = Validation::new("https://service-abc.a.run.app/");
+    assert!(!validation.audience_matches("https://service-abc.a.run.app/tasks"));
+    validation.audience_match = AudienceMatch::UrlPrefix;
+    assert!(validation.audience_matches("https://service-abc.a.run.app"));
+    assert!(validation.audience_matches("https://service-abc.a.run.app/tasks/run"));
+    assert!(!validation.audience_matches("https://service-abc.a.run.app.evil.com"));
+    assert!(!validation.audience_matches("https://service-abc.a.run.apps"));
+}